@@ -77,19 +77,13 @@ pub mod ballistic {
         }
 
         fn new_unused() -> Self {
-            let mass: Real = 0.0;
-            let velocity = Vec3::default();
-            let acceleration = Vec3::default();
             let radio: Real = 0.0;
-            let damping: Real = 0.0;
-
-            let mut shot = Shot::new(ShottingType::UNUSED, mass, velocity, acceleration, radio, damping);
+            let start_time: Real = 0.0;
 
-            // Set the position to the center
-            shot.particle.set_position(Vec3::default());
+            // Unused shots are immovable and stay at the center
+            let particle = Particle::new_static(Vec3::default());
 
-            // Returning the unused shot
-            shot
+            Shot { shot_type: ShottingType::UNUSED, start_time, particle, radio }
         }
 
         pub fn from(shot_type: ShottingType) -> Self {
@@ -116,8 +110,13 @@ pub mod ballistic {
 
 fn main() {
     use ballistic::{ ShottingType, Shot };
+    use rusty_cyclone::core::Vec3;
     use rusty_cyclone::precision::*;
     use rusty_cyclone::particle::*;
+    use rusty_cyclone::particle_contacts::*;
+    use rusty_cyclone::clock::SimulationClock;
+    use rusty_cyclone::collide_fine::CollisionPlane;
+    use rusty_cyclone::query::*;
     use raylib::prelude::*;
 
     let window_width: i32 = 480;
@@ -146,8 +145,29 @@ fn main() {
     let mut shot = Shot::from(ShottingType::ARTILLERY);
     shot.set_start_time(rl.get_frame_time() as Real);
 
+    // The bottom of the window is the ground, the y axis points down
+    let mut ground = ParticleHalfSpaceContacts::new(
+        Vec3::new(0, -1, 0), -(window_height as Real), 0.6, 0.1
+    );
+    ground.set_radius(shot.radio);
+
+    // Lasers travel instantly, so they are cast as rays up to the
+    // right side of the window instead of being integrated
+    let wall = CollisionPlane::new(Vec3::new(-1, 0, 0), -(window_width as Real));
+
+    let mut resolver = ParticleContactResolver::new(2);
+    let mut contacts: Vec<ParticleContact> = Vec::new();
+
+    // Simulate at a fixed rate, whatever the frame rate is
+    let mut clock = SimulationClock::new(1.0 / 120.0, 8);
+
+    // Lasers are only shown for a moment after being shot
+    let laser_time: Real = 0.5;
+    let mut time: Real = shot.start_time;
+
     while !rl.window_should_close() {
         let delta: Real = rl.get_frame_time() as Real;
+        time += delta;
         let mut d = rl.begin_drawing(&thd);
         d.clear_background(Color::GRAY);
 
@@ -156,23 +176,39 @@ fn main() {
             let position = shot.particle.get_position();
             let x = position.x as i32;
             let y = position.y as i32;
-            let radio = shot.radio as f32;
-            mode.draw_circle(x, y, radio, Color::RAYWHITE);
+
+            if shot.shot_type == ShottingType::LASER {
+                let ray = Ray::new(position, shot.particle.get_velocity());
+
+                if let Some(hit) = raycast_plane(&ray, &wall, REAL_MAX) {
+                    mode.draw_line(x, y, hit.point.x as i32, hit.point.y as i32, Color::RED);
+                }
+            } else {
+                let radio = shot.radio as f32;
+                mode.draw_circle(x, y, radio, Color::RAYWHITE);
+            }
         }
 
-        if shot.shot_type != ShottingType::UNUSED {
-            shot.particle.integrate(delta);
+        if shot.shot_type != ShottingType::UNUSED && shot.shot_type != ShottingType::LASER {
+            clock.advance(delta, |step| {
+                shot.particle.integrate(step);
+
+                // Bounce the shot on the ground
+                contacts.clear();
+                ground.add_contact(std::slice::from_ref(&shot.particle), &mut contacts, 1);
+                resolver.resolve_contacts(&mut contacts, std::slice::from_mut(&mut shot.particle), step);
+            });
 
-            let win_y_limit: Real = (window_height as Real) - shot.radio;
             let win_x_limit: Real = (window_width as Real) + shot.radio;
             let time_limit:  Real = shot.start_time + 5000.0;
             let position = shot.particle.get_position();
 
-            if position.y > win_y_limit ||
-               position.x > win_x_limit ||
+            if position.x > win_x_limit ||
                time_limit < delta {
                 shot.set_shot_type(ShottingType::UNUSED);
             }
+        } else if shot.shot_type == ShottingType::LASER && time - shot.start_time > laser_time {
+            shot.set_shot_type(ShottingType::UNUSED);
         }
     }
 }
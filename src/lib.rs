@@ -1,7 +1,22 @@
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
     use super::core::*;
     use super::precision::*;
+    use super::particle::*;
+    use super::particle_forces::*;
+    use super::particle_contacts::*;
+    use super::particle_links::*;
+    use super::particle_world::*;
+    use super::clock::*;
+    use super::rigid_body::*;
+    use super::forces::*;
+    use super::inertia;
+    use super::collide_fine::*;
+    use super::contacts::*;
+    use super::collide_coarse::*;
+    use super::query::*;
 
     #[test]
     fn vec3_invert() {
@@ -48,6 +63,22 @@ mod tests {
         assert_eq!(5 as Real, x.as_real());
     }
 
+    #[test]
+    fn real_constants_match_precision() {
+        assert_eq!(REAL_EPSILON, Real::EPSILON);
+        assert_eq!(REAL_MAX, Real::MAX);
+        assert!((1.0 as Real + REAL_EPSILON) > 1.0);
+    }
+
+    #[cfg(feature = "double-precision")]
+    #[test]
+    fn double_precision_far_from_origin() {
+        let mut vec = Vec3::new(1.0e8, 0, 0);
+        vec += 0.01;
+        assert_eq!(std::mem::size_of::<Real>(), 8);
+        assert!((vec.x - 1.0e8 - 0.01).abs() < 1e-6);
+    }
+
     #[test]
     fn add_scalar_vector() {
         let mut v: Vec3 = Default::default();
@@ -69,505 +100,8048 @@ mod tests {
         let v3 = v1.dot(v2);
         assert_eq!(v3, 12 as Real);
     }
-}
 
-pub mod precision {
-    /// Defines the real number precision.
-    /// It can be f32 or f64, simple and double
-    /// precisions repectively.
-    pub type Real = f32;
+    fn assert_vec3_near(left: Vec3, right: Vec3) {
+        assert!((left - right).magnitude() < 1e-4, "{:?} != {:?}", left, right);
+    }
 
-    /// Trait for values that can be converted to the real type
-    pub trait AsReal {
-        fn as_real(self) -> Real;
+    #[test]
+    fn quaternion_rotates_vector() {
+        let q = Quaternion::from_axis_angle(Vec3::new(0, 0, 1), REAL_PI / 2.0);
+        assert_vec3_near(q.rotate(Vec3::new(1, 0, 0)), Vec3::new(0, 1, 0));
     }
 
-    impl AsReal for i8 {
-        fn as_real(self) -> Real {
-            self as Real
-        }
+    #[test]
+    fn quaternion_axis_angle_roundtrip() {
+        let q = Quaternion::from_axis_angle(Vec3::new(1, 1, 0), 0.8);
+        let (axis, angle) = q.to_axis_angle();
+
+        let mut expected = Vec3::new(1, 1, 0);
+        expected.normalize();
+        assert_vec3_near(axis, expected);
+        assert!((angle - 0.8).abs() < 1e-4);
     }
 
-    impl AsReal for i16 {
-        fn as_real(self) -> Real {
-            self as Real
-        }
+    #[test]
+    fn quaternion_euler_roundtrip() {
+        let q = Quaternion::from_euler(0.3, -0.2, 1.1);
+        assert_vec3_near(q.to_euler(), Vec3::new(0.3, -0.2, 1.1));
+
+        // Yaw is applied first, then pitch and roll
+        let composed = Quaternion::from_axis_angle(Vec3::new(0, 0, 1), 1.1)
+            * Quaternion::from_axis_angle(Vec3::new(0, 1, 0), -0.2)
+            * Quaternion::from_axis_angle(Vec3::new(1, 0, 0), 0.3);
+        assert!((composed.dot(q) - 1.0).abs() < 1e-4);
     }
 
-    impl AsReal for i32 {
-        fn as_real(self) -> Real {
-            self as Real
-        }
+    #[test]
+    fn quaternion_slerp_halfway() {
+        let from = Quaternion::default();
+        let to = Quaternion::from_axis_angle(Vec3::new(0, 1, 0), 1.0);
+        let half = from.slerp(to, 0.5);
+        let (_, angle) = half.to_axis_angle();
+        assert!((angle - 0.5).abs() < 1e-4);
     }
 
-    impl AsReal for i64 {
-        fn as_real(self) -> Real {
-            self as Real
+    #[test]
+    fn quaternion_integrates_angular_velocity() {
+        let mut q = Quaternion::default();
+
+        for _ in 0..1000 {
+            q.add_scaled_vector(Vec3::new(0, 0, 1), 0.001);
+            q.normalize();
         }
+
+        let (axis, angle) = q.to_axis_angle();
+        assert_vec3_near(axis, Vec3::new(0, 0, 1));
+        assert!((angle - 1.0).abs() < 1e-3);
     }
 
-    impl AsReal for i128 {
-        fn as_real(self) -> Real {
-            self as Real
-        }
+    #[test]
+    fn matrix3_inverse_and_transpose() {
+        let m = Matrix3::new([2.0, 0.0, 1.0, 1.0, 3.0, 0.0, 0.0, 1.0, 4.0]);
+        let inverse = m.inverse().unwrap();
+        let v = Vec3::new(1, -2, 3);
+
+        assert_vec3_near(inverse * (m * v), v);
+        assert_vec3_near(m.transpose() * v, m.transform_transpose(v));
+        assert!(Matrix3::zero().inverse().is_none());
     }
 
-    impl AsReal for u8 {
-        fn as_real(self) -> Real {
-            self as Real
-        }
+    #[test]
+    fn matrix3_skew_symmetric_is_vector_product() {
+        let a = Vec3::new(1, 2, 3);
+        let b = Vec3::new(-2, 0.5, 4);
+        assert_vec3_near(Matrix3::from_skew_symmetric(a) * b, a.vec_prod(b));
     }
 
-    impl AsReal for u16 {
-        fn as_real(self) -> Real {
-            self as Real
-        }
+    #[test]
+    fn matrix3_from_quaternion_matches_rotation() {
+        let q = Quaternion::from_euler(0.4, 1.2, -0.7);
+        let v = Vec3::new(3, -1, 2);
+        assert_vec3_near(Matrix3::from_quaternion(q) * v, q.rotate(v));
     }
 
-    impl AsReal for u32 {
-        fn as_real(self) -> Real {
-            self as Real
-        }
+    #[test]
+    fn matrix4_transform_roundtrip() {
+        let q = Quaternion::from_axis_angle(Vec3::new(0, 0, 1), REAL_PI / 2.0);
+        let transform = Matrix4::from_orientation_and_pos(q, Vec3::new(1, 2, 3));
+        let point = Vec3::new(1, 0, 0);
+
+        let world = transform * point;
+        assert_vec3_near(world, Vec3::new(1, 3, 3));
+        assert_vec3_near(transform.transform_inverse(world), point);
+        assert_vec3_near(transform.inverse().unwrap() * world, point);
+        assert_vec3_near(transform.transform_direction(point), Vec3::new(0, 1, 0));
+        assert_vec3_near(transform.transform_inverse_direction(Vec3::new(0, 1, 0)), point);
     }
 
-    impl AsReal for u64 {
-        fn as_real(self) -> Real {
-            self as Real
-        }
+    #[test]
+    fn matrix4_composition() {
+        let a = Matrix4::from_orientation_and_pos(Quaternion::from_euler(0.1, 0.2, 0.3), Vec3::new(1, 0, 0));
+        let b = Matrix4::from_orientation_and_pos(Quaternion::from_euler(-0.5, 0.0, 1.0), Vec3::new(0, 2, -1));
+        let point = Vec3::new(0.5, -1, 2);
+        assert_vec3_near((a * b) * point, a * (b * point));
     }
 
-    impl AsReal for u128 {
-        fn as_real(self) -> Real {
-            self as Real
-        }
+    #[test]
+    fn rigid_body_force_at_body_point_spins_body() {
+        let mut body = RigidBody::new(2, Matrix3::from_diagonal(1, 1, 1));
+        body.set_position(Vec3::new(5, 0, 0));
+        body.add_force_at_body_point(Vec3::new(0, 2, 0), Vec3::new(1, 0, 0));
+
+        assert_eq!(body.get_force_accumulator(), Vec3::new(0, 2, 0));
+        assert_eq!(body.get_torque_accumulator(), Vec3::new(0, 0, 2));
+
+        body.integrate(0.5);
+
+        assert_vec3_near(body.get_velocity(), Vec3::new(0, 0.5, 0));
+        assert_vec3_near(body.get_rotation(), Vec3::new(0, 0, 1));
+        assert_eq!(body.get_torque_accumulator(), Vec3::default());
+        let (axis, angle) = body.get_orientation().to_axis_angle();
+        assert_vec3_near(axis, Vec3::new(0, 0, 1));
+        assert!((angle - 0.5).abs() < 0.05);
     }
 
-    impl AsReal for f32 {
-        fn as_real(self) -> Real {
-            self as Real
-        }
+    #[test]
+    fn rigid_body_world_inertia_follows_orientation() {
+        let mut body = RigidBody::new(1, Matrix3::from_diagonal(1, 2, 4));
+        body.set_orientation(Quaternion::from_axis_angle(Vec3::new(0, 0, 1), REAL_PI / 2.0));
+
+        // The body x axis now points along the world y axis
+        let world_inverse = body.get_inverse_inertia_tensor_world();
+        assert_vec3_near(world_inverse * Vec3::new(0, 1, 0), Vec3::new(0, 1, 0));
+        assert_vec3_near(world_inverse * Vec3::new(1, 0, 0), Vec3::new(0.5, 0, 0));
+        assert_vec3_near(body.get_point_in_world_space(Vec3::new(1, 0, 0)), Vec3::new(0, 1, 0));
     }
 
-    impl AsReal for f64 {
-        fn as_real(self) -> Real {
-            self as Real
-        }
+    #[test]
+    fn rigid_body_registry_applies_gravity_and_spring() {
+        let mut bodies = vec![RigidBody::default(), RigidBody::new_static(Vec3::new(0, 4, 0), Quaternion::default())];
+        let mut registry = ForceRegistry::new();
+        registry.add(0, Rc::new(RefCell::new(Gravity::new(Vec3::new(0, -10, 0)))));
+        registry.add(1, Rc::new(RefCell::new(Gravity::new(Vec3::new(0, -10, 0)))));
+        registry.add(0, Rc::new(RefCell::new(Spring::new(Vec3::new(1, 0, 0), 1, Vec3::default(), 2, 3))));
+
+        registry.update_forces(&mut bodies, 0.1);
+
+        // The spring is stretched from (1, 0, 0) to (0, 4, 0) by sqrt(17) - 3
+        let stretch = (17.0 as Real).sqrt() - 3.0;
+        let mut direction = Vec3::new(-1, 4, 0);
+        direction.normalize();
+        assert_vec3_near(bodies[0].get_force_accumulator(), Vec3::new(0, -10, 0) + direction * (2.0 * stretch));
+        assert_vec3_near(bodies[0].get_torque_accumulator(), Vec3::new(0, 0, 4.0 * 2.0 * stretch / (17.0 as Real).sqrt()));
+        assert_eq!(bodies[1].get_force_accumulator(), Vec3::default());
     }
-}
 
-pub mod core {
-    use std::ops::*;
-    use super::precision::*;
-    use rand::Rng;
+    #[test]
+    fn aero_control_interpolates_tensor() {
+        let base = Matrix3::from_diagonal(0, -1, 0);
+        let min = Matrix3::from_diagonal(0, -2, 0);
+        let max = Matrix3::zero();
+        let mut control = AeroControl::new(base, min, max, Vec3::default(), Vec3::default());
 
-    #[allow(unused)]
-    #[derive(Debug, Copy, Clone, PartialEq)]
-    /// Tri-dimensional vector composed of three elements
-    /// for the x, y and z axes.
-    pub struct Vec3 {
-        pub x: Real,
-        pub y: Real,
-        pub z: Real,
-        pad: Real
+        control.set_control(-0.5);
+        assert_eq!(control.get_tensor(), Matrix3::from_diagonal(0, -1.5, 0));
+
+        // Falling through still air generates a lifting drag
+        let mut bodies = vec![RigidBody::default()];
+        bodies[0].set_velocity(Vec3::new(0, -2, 0));
+        control.update_force(&mut bodies, 0, 0.1);
+        assert_vec3_near(bodies[0].get_force_accumulator(), Vec3::new(0, 3, 0));
     }
 
-    impl Default for Vec3 {
-        /// Returns a new Vector with all elements set to zero.
-        fn default() -> Self {
-            Self::new(0., 0., 0.)
+    #[test]
+    fn buoyancy_depends_on_submersion() {
+        let mut buoyancy = Buoyancy::new(Vec3::default(), 0.5, 2, 0, 1000);
+        let mut bodies = vec![RigidBody::default(), RigidBody::default(), RigidBody::default()];
+        bodies[0].set_position(Vec3::new(0, 1, 0));
+        bodies[1].set_position(Vec3::new(0, 0, 0));
+        bodies[2].set_position(Vec3::new(0, -1, 0));
+
+        for index in 0..3 {
+            buoyancy.update_force(&mut bodies, index, 0.1);
         }
+
+        assert_eq!(bodies[0].get_force_accumulator(), Vec3::default());
+        assert_vec3_near(bodies[1].get_force_accumulator(), Vec3::new(0, 1000, 0));
+        assert_vec3_near(bodies[2].get_force_accumulator(), Vec3::new(0, 2000, 0));
     }
 
-    impl Vec3 {
-        /// Creates a new Vec3, defining the the values for all axes
-        ///
-        /// # Arguments
-        /// * `x` - value for the x axis
-        /// * `y` - value for the y axis
-        /// * `z` - value for the z axis
-        ///
-        /// # Examples
-        /// ```
-        /// use rusty_cyclone::core::Vec3;
-        /// let vec = Vec3::new(-1, 3.98, 4);
-        /// ```
-        pub fn new<T1, T2, T3>(x: T1, y: T2, z: T3) -> Self
-        where T1: AsReal, T2: AsReal, T3: AsReal {
-            Self {
-                x: x.as_real(),
-                y: y.as_real(),
-                z: z.as_real(),
-                pad: 0 as Real
-            }
+    fn assert_matrix3_near(left: Matrix3, right: Matrix3) {
+        for i in 0..9 {
+            assert!((left.data[i] - right.data[i]).abs() < 1e-3, "{:?} != {:?}", left, right);
         }
+    }
 
-        /// Generates a vector with random elements from a given range.
-        ///
-        /// ### Arguments
-        /// * `range` - The range of which element will by randomly assigned.
-        pub fn from_range(range: Range<i32>) -> Self {
-            let mut rng = rand::thread_rng();
+    #[test]
+    fn inertia_of_primitive_shapes() {
+        assert_matrix3_near(inertia::solid_sphere(5, 2), Matrix3::from_diagonal(8, 8, 8));
+        assert_matrix3_near(inertia::hollow_sphere(3, 1), Matrix3::from_diagonal(2, 2, 2));
+        assert_matrix3_near(inertia::solid_box(3, Vec3::new(1, 2, 3)), Matrix3::from_diagonal(13, 10, 5));
+        assert_matrix3_near(inertia::solid_cylinder(4, 1, 3), Matrix3::from_diagonal(4, 2, 4));
+        assert_matrix3_near(inertia::solid_cone(10, 2, 4), Matrix3::from_diagonal(12, 12, 12));
 
-            let range1 = rng.gen_range(range.clone());
-            let range2 = rng.gen_range(range.clone());
-            let range3 = rng.gen_range(range);
+        // A capsule without a cylinder is a sphere
+        assert_matrix3_near(inertia::solid_capsule(5, 2, 0), inertia::solid_sphere(5, 2));
+    }
 
-            Self::new(range1, range2, range3)
-        }
+    #[test]
+    fn inertia_of_hollow_cube_faces() {
+        // Each face of a cube with unit half-size has m/6 of the mass
+        let faces = 6.0 as Real;
+        let expected = 2.0 * (1.0 / 3.0 + 1.0 / 3.0) / faces
+            + 2.0 * (1.0 / 3.0 + 1.0) / faces * 2.0;
+        let moment = expected * 6.0;
+        assert_matrix3_near(inertia::hollow_box(6, Vec3::new(1, 1, 1)), Matrix3::from_diagonal(moment, moment, moment));
     }
 
-    impl Add<Vec3> for Vec3 {
-        type Output = Self;
+    #[test]
+    fn inertia_combines_parts_with_parallel_axis() {
+        let sphere = inertia::solid_sphere(1, 1);
+        let parts = [
+            inertia::MassProperties::new(1, Vec3::new(-2, 0, 0), sphere),
+            inertia::MassProperties::new(1, Vec3::new(2, 0, 0), sphere),
+        ];
 
-        fn add(self, other: Vec3) -> Self::Output {
-            Self::new(
-                self.x + other.x,
-                self.y + other.y,
-                self.z + other.z
-            )
-        }
+        let combined = inertia::combine(&parts);
+
+        assert_eq!(combined.mass, 2.0);
+        assert_vec3_near(combined.centre_of_mass, Vec3::default());
+        assert_matrix3_near(combined.inertia_tensor, Matrix3::from_diagonal(0.8, 8.8, 8.8));
+        assert_matrix3_near(inertia::from_coeffs(1, 2, 3, 0.5, 0, 0), Matrix3::new([1.0, -0.5, 0.0, -0.5, 2.0, 0.0, 0.0, 0.0, 3.0]));
     }
 
-    impl<T: AsReal> Add<T> for Vec3 {
-        type Output = Self;
+    #[test]
+    fn primitives_follow_their_body() {
+        let mut bodies = vec![RigidBody::default()];
+        bodies[0].set_position(Vec3::new(1, 2, 3));
+        bodies[0].set_orientation(Quaternion::from_axis_angle(Vec3::new(0, 0, 1), REAL_PI / 2.0));
 
-        fn add(self, other: T) -> Self::Output {
-            let other = other.as_real();
+        let offset = Matrix4::from_orientation_and_pos(Quaternion::default(), Vec3::new(1, 0, 0));
+        let mut sphere = CollisionSphere::new(Some(0), offset, 0.5);
+        let mut capsule = CollisionCapsule::new(Some(0), Matrix4::default(), 0.5, 2);
+        sphere.primitive.calculate_internals(&bodies);
+        capsule.primitive.calculate_internals(&bodies);
 
-            Self::new(
-                self.x + other,
-                self.y + other,
-                self.z + other
-            )
-        }
+        assert_vec3_near(sphere.primitive.get_position(), Vec3::new(1, 3, 3));
+        let (start, end) = capsule.get_segment();
+        assert_vec3_near(start, Vec3::new(3, 2, 3));
+        assert_vec3_near(end, Vec3::new(-1, 2, 3));
     }
 
-    impl AddAssign<Vec3> for Vec3{
-        fn add_assign(&mut self, other: Vec3) {
-            *self = Self::new(
-                self.x + other.x,
-                self.y + other.y,
-                self.z + other.z
-            );
-        }
+    #[test]
+    fn box_vertices_in_world_space() {
+        let offset = Matrix4::from_orientation_and_pos(Quaternion::default(), Vec3::new(0, 5, 0));
+        let mut cube = CollisionBox::new(None, offset, Vec3::new(1, 2, 3));
+        cube.primitive.calculate_internals(&[]);
+
+        let vertices = cube.get_vertices();
+        assert_vec3_near(vertices[0], Vec3::new(-1, 3, -3));
+        assert_vec3_near(vertices[7], Vec3::new(1, 7, 3));
     }
 
-    impl<T: AsReal> AddAssign<T> for Vec3 {
-        fn add_assign(&mut self, other: T) {
-            let other = other.as_real();
+    fn placed(position: Vec3, orientation: Quaternion) -> Matrix4 {
+        Matrix4::from_orientation_and_pos(orientation, position)
+    }
 
-            *self = Self::new(
-                self.x + other,
-                self.y + other,
-                self.z + other,
-            )
-        }
+    #[test]
+    fn sphere_and_sphere_contact() {
+        let bodies = vec![RigidBody::default(); 2];
+        let mut one = CollisionSphere::new(Some(0), placed(Vec3::new(0, 0, 0), Quaternion::default()), 1);
+        let mut two = CollisionSphere::new(Some(1), placed(Vec3::new(1.5, 0, 0), Quaternion::default()), 1);
+        one.primitive.calculate_internals(&bodies);
+        two.primitive.calculate_internals(&bodies);
+        let mut data = CollisionData::new(4);
+        data.friction = 0.5;
+
+        assert!(IntersectionTests::sphere_and_sphere(&one, &two));
+        assert_eq!(CollisionDetector::sphere_and_sphere(&one, &two, &mut data), 1);
+
+        let contact = &data.get_contacts()[0];
+        assert_eq!(contact.bodies, [Some(0), Some(1)]);
+        assert_vec3_near(contact.contact_normal, Vec3::new(-1, 0, 0));
+        assert_vec3_near(contact.contact_point, Vec3::new(0.75, 0, 0));
+        assert!((contact.penetration - 0.5).abs() < 1e-4);
+        assert_eq!(contact.friction, 0.5);
     }
 
-    impl Sub<Vec3> for Vec3 {
-        type Output = Self;
+    #[test]
+    fn box_resting_on_half_space() {
+        let bodies = vec![RigidBody::default(); 2];
+        let mut cube = CollisionBox::new(Some(0), placed(Vec3::new(0, 0.9, 0), Quaternion::default()), Vec3::new(1, 1, 1));
+        cube.primitive.calculate_internals(&bodies);
+        let ground = CollisionPlane::new(Vec3::new(0, 1, 0), 0);
+        let mut data = CollisionData::new(8);
 
-        fn sub(self, other: Vec3) -> Self::Output {
-            Self::new(
-                self.x - other.x,
-                self.y - other.y,
-                self.z - other.z,
-            )
+        assert_eq!(CollisionDetector::box_and_half_space(&cube, &ground, &mut data), 4);
+        for contact in data.get_contacts() {
+            assert_vec3_near(contact.contact_normal, Vec3::new(0, 1, 0));
+            assert!((contact.penetration - 0.1).abs() < 1e-4);
         }
+
+        // The buffer stops accepting contacts once it is full
+        let mut data = CollisionData::new(2);
+        assert_eq!(CollisionDetector::box_and_half_space(&cube, &ground, &mut data), 2);
+        assert!(!data.has_more_contacts());
     }
 
-    impl<T: AsReal> Sub<T> for Vec3 {
-        type Output = Self;
+    #[test]
+    fn box_and_sphere_contact() {
+        let bodies = vec![RigidBody::default(); 2];
+        let mut cube = CollisionBox::new(Some(0), Matrix4::default(), Vec3::new(1, 1, 1));
+        let mut sphere = CollisionSphere::new(Some(1), placed(Vec3::new(0, 1.5, 0), Quaternion::default()), 1);
+        cube.primitive.calculate_internals(&bodies);
+        sphere.primitive.calculate_internals(&bodies);
+        let mut data = CollisionData::new(4);
 
-        fn sub(self, other: T) -> Self::Output {
-            let other = other.as_real();
+        assert_eq!(CollisionDetector::box_and_sphere(&cube, &sphere, &mut data), 1);
 
-            Self::new(
-                self.x - other,
-                self.y - other,
-                self.z - other,
-            )
-        }
+        let contact = &data.get_contacts()[0];
+        assert_vec3_near(contact.contact_point, Vec3::new(0, 1, 0));
+        assert_vec3_near(contact.contact_normal, Vec3::new(0, -1, 0));
+        assert!((contact.penetration - 0.5).abs() < 1e-4);
     }
 
-    impl SubAssign<Vec3> for Vec3 {
-        fn sub_assign(&mut self, other: Vec3) {
-            *self = Self::new(
-                self.x - other.x,
-                self.y - other.y,
-                self.z - other.z
-            );
-        }
-    }
+    #[test]
+    fn box_and_box_face_contact() {
+        let bodies = vec![RigidBody::default(); 2];
+        let mut one = CollisionBox::new(Some(0), Matrix4::default(), Vec3::new(1, 1, 1));
+        let mut two = CollisionBox::new(Some(1), placed(Vec3::new(0, 1.8, 0), Quaternion::default()), Vec3::new(0.5, 1, 0.5));
+        one.primitive.calculate_internals(&bodies);
+        two.primitive.calculate_internals(&bodies);
+        let mut data = CollisionData::new(4);
 
-    impl<T: AsReal> SubAssign<T> for Vec3 {
-        fn sub_assign(&mut self, other: T) {
-            let other = other.as_real();
+        assert!(IntersectionTests::box_and_box(&one, &two));
+        assert_eq!(CollisionDetector::box_and_box(&one, &two, &mut data), 1);
 
-            *self = Self::new(
-                self.x - other,
-                self.y - other,
-                self.z - other
-            );
-        }
-    }
+        let contact = &data.get_contacts()[0];
+        assert_vec3_near(contact.contact_normal, Vec3::new(0, -1, 0));
+        assert!((contact.penetration - 0.2).abs() < 1e-4);
 
-    impl<T: AsReal> Div<T> for Vec3 {
-        type Output = Self;
+        two.primitive.offset = placed(Vec3::new(0, 2.5, 0), Quaternion::default());
+        two.primitive.calculate_internals(&bodies);
+        assert!(!IntersectionTests::box_and_box(&one, &two));
+        assert_eq!(CollisionDetector::box_and_box(&one, &two, &mut data), 0);
+    }
 
-        fn div(self, other: T) -> Self {
-            let other = other.as_real();
+    #[test]
+    fn box_and_box_edge_contact() {
+        let bodies = vec![RigidBody::default(); 2];
+        // Two cubes turned so that their edges cross above each other
+        let tilt = Quaternion::from_axis_angle(Vec3::new(0, 0, 1), REAL_PI / 4.0);
+        let twist = Quaternion::from_axis_angle(Vec3::new(0, 1, 0), REAL_PI / 2.0) * tilt;
+        let height = (2 as Real).sqrt() * 2.0 - 0.1;
+        let mut one = CollisionBox::new(Some(0), placed(Vec3::default(), tilt), Vec3::new(1, 1, 1));
+        let mut two = CollisionBox::new(Some(1), placed(Vec3::new(0, height, 0), twist), Vec3::new(1, 1, 1));
+        one.primitive.calculate_internals(&bodies);
+        two.primitive.calculate_internals(&bodies);
+        let mut data = CollisionData::new(4);
 
-            if other.as_real() == 0 as Real {
-                panic!("Division by zero!");
-            }
+        assert_eq!(CollisionDetector::box_and_box(&one, &two, &mut data), 1);
 
-            Self::new(
-                self.x / other,
-                self.y / other,
-                self.z / other
-            )
-        }
+        let contact = &data.get_contacts()[0];
+        assert_vec3_near(contact.contact_normal, Vec3::new(0, -1, 0));
+        assert_vec3_near(contact.contact_point, Vec3::new(0, height / 2.0, 0));
+        assert!((contact.penetration - 0.1).abs() < 1e-3);
     }
 
-    impl<T: AsReal> DivAssign<T> for Vec3 {
-        fn div_assign(&mut self, other: T) {
-            let other = other.as_real();
+    #[test]
+    fn capsule_contacts() {
+        let bodies = vec![RigidBody::default(); 2];
+        let lying = Quaternion::from_axis_angle(Vec3::new(0, 0, 1), REAL_PI / 2.0);
+        let mut capsule = CollisionCapsule::new(Some(0), placed(Vec3::new(0, 0.4, 0), lying), 0.5, 1);
+        let mut other = CollisionCapsule::new(Some(1), placed(Vec3::new(0, 1.3, 0), lying), 0.5, 1);
+        capsule.primitive.calculate_internals(&bodies);
+        other.primitive.calculate_internals(&bodies);
+        let ground = CollisionPlane::new(Vec3::new(0, 1, 0), 0);
+        let mut data = CollisionData::new(8);
 
-            if other == 0 as Real {
-                panic!("Division by zero!");
-            }
+        // Lying capsules touch the ground at both ends
+        assert_eq!(CollisionDetector::capsule_and_half_space(&capsule, &ground, &mut data), 2);
+        assert!((data.get_contacts()[0].penetration - 0.1).abs() < 1e-4);
 
-            *self = Self::new(
-                self.x / other,
+        data.reset();
+        assert_eq!(CollisionDetector::capsule_and_capsule(&other, &capsule, &mut data), 1);
+        let contact = &data.get_contacts()[0];
+        assert_eq!(contact.bodies, [Some(1), Some(0)]);
+        assert_vec3_near(contact.contact_normal, Vec3::new(0, 1, 0));
+        assert!((contact.penetration - 0.1).abs() < 1e-4);
+    }
+
+    #[test]
+    fn capsule_standing_on_box() {
+        let bodies = vec![RigidBody::default(); 2];
+        let mut cube = CollisionBox::new(Some(0), Matrix4::default(), Vec3::new(1, 1, 1));
+        let mut capsule = CollisionCapsule::new(Some(1), placed(Vec3::new(0, 2.3, 0), Quaternion::default()), 0.5, 1);
+        cube.primitive.calculate_internals(&bodies);
+        capsule.primitive.calculate_internals(&bodies);
+        let mut data = CollisionData::new(4);
+
+        // Only the lower end touches the top face of the box
+        assert_eq!(CollisionDetector::capsule_and_box(&capsule, &cube, &mut data), 1);
+
+        let contact = &data.get_contacts()[0];
+        assert_eq!(contact.bodies, [Some(0), Some(1)]);
+        assert_vec3_near(contact.contact_point, Vec3::new(0, 1, 0));
+        assert_vec3_near(contact.contact_normal, Vec3::new(0, -1, 0));
+        assert!((contact.penetration - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn box_and_point_contact() {
+        let bodies = vec![RigidBody::default(); 1];
+        let mut cube = CollisionBox::new(Some(0), Matrix4::default(), Vec3::new(1, 1, 1));
+        cube.primitive.calculate_internals(&bodies);
+        let mut data = CollisionData::new(4);
+
+        // The box is pushed away from the point, like a sphere inside it
+        assert_eq!(CollisionDetector::box_and_point(&cube, Vec3::new(0.9, 0, 0), &mut data), 1);
+
+        let contact = &data.get_contacts()[0];
+        assert_vec3_near(contact.contact_normal, Vec3::new(-1, 0, 0));
+        assert!((contact.penetration - 0.1).abs() < 1e-4);
+
+        assert_eq!(CollisionDetector::box_and_point(&cube, Vec3::new(1.5, 0, 0), &mut data), 0);
+    }
+
+    fn sphere_body(position: Vec3, velocity: Vec3) -> RigidBody {
+        let mut body = RigidBody::new(1, inertia::solid_sphere(1, 1));
+        body.set_position(position);
+        body.set_velocity(velocity);
+        body
+    }
+
+    #[test]
+    fn resolver_bounces_spheres_apart() {
+        let mut bodies = vec![
+            sphere_body(Vec3::new(-0.9, 0, 0), Vec3::new(1, 0, 0)),
+            sphere_body(Vec3::new(0.9, 0, 0), Vec3::new(-1, 0, 0)),
+        ];
+        let mut contacts = vec![
+            Contact::new([Some(0), Some(1)], Vec3::default(), Vec3::new(-1, 0, 0), 0.2, 0, 1)
+        ];
+        let mut resolver = ContactResolver::new(4, 4);
+
+        resolver.resolve_contacts(&mut contacts, &mut bodies, 0.01);
+
+        // Equal masses with full restitution exchange their velocities
+        assert_vec3_near(bodies[0].get_velocity(), Vec3::new(-1, 0, 0));
+        assert_vec3_near(bodies[1].get_velocity(), Vec3::new(1, 0, 0));
+        assert_vec3_near(bodies[0].get_rotation(), Vec3::default());
+        assert_vec3_near(bodies[0].get_position(), Vec3::new(-1, 0, 0));
+        assert_vec3_near(bodies[1].get_position(), Vec3::new(1, 0, 0));
+        assert_eq!(resolver.get_velocity_iterations_used(), 1);
+        assert_eq!(resolver.get_position_iterations_used(), 1);
+    }
+
+    #[test]
+    fn resolver_handles_scenery_in_either_slot() {
+        let mut bodies = vec![sphere_body(Vec3::new(0, 0.9, 0), Vec3::new(0, -2, 0))];
+        let mut contacts = vec![
+            Contact::new([None, Some(0)], Vec3::new(0, -0.05, 0), Vec3::new(0, -1, 0), 0.1, 0, 0)
+        ];
+
+        ContactResolver::new(4, 4).resolve_contacts(&mut contacts, &mut bodies, 0.01);
+
+        // The contact is flipped so the body is pushed up out of the ground
+        assert_eq!(contacts[0].bodies, [Some(0), None]);
+        assert_vec3_near(bodies[0].get_velocity(), Vec3::default());
+        assert_vec3_near(bodies[0].get_position(), Vec3::new(0, 1, 0));
+    }
+
+    #[test]
+    fn resolver_friction_stops_sliding_contact() {
+        let ground_contact = |friction: Real| {
+            vec![Contact::new([Some(0), None], Vec3::new(0, -1, 0), Vec3::new(0, 1, 0), 0, friction, 0)]
+        };
+
+        // Without friction the sliding is untouched
+        let mut bodies = vec![sphere_body(Vec3::default(), Vec3::new(1, -1, 0))];
+        ContactResolver::new(4, 4).resolve_contacts(&mut ground_contact(0.0), &mut bodies, 0.01);
+        assert_vec3_near(bodies[0].get_velocity(), Vec3::new(1, 0, 0));
+        assert_vec3_near(bodies[0].get_rotation(), Vec3::default());
+
+        // With enough friction the contact point ends up at rest, with the
+        // sphere rolling
+        let mut bodies = vec![sphere_body(Vec3::default(), Vec3::new(1, -1, 0))];
+        let mut contacts = ground_contact(10.0);
+        ContactResolver::new(4, 4).resolve_contacts(&mut contacts, &mut bodies, 0.01);
+        let body = &bodies[0];
+        let point_velocity = body.get_velocity() + body.get_rotation().vec_prod(Vec3::new(0, -1, 0));
+        assert_vec3_near(point_velocity, Vec3::default());
+        assert!(body.get_rotation().z < 0 as Real);
+
+        // With little friction the sliding is only slowed
+        let mut bodies = vec![sphere_body(Vec3::default(), Vec3::new(1, -1, 0))];
+        ContactResolver::new(4, 4).resolve_contacts(&mut ground_contact(0.1), &mut bodies, 0.01);
+        let velocity = bodies[0].get_velocity();
+        assert!(velocity.x > 0.85 && velocity.x < 0.95, "{:?}", velocity);
+        assert!(velocity.y.abs() < 1e-4);
+    }
+
+    #[test]
+    fn resting_particle_falls_asleep() {
+        let mut world = ParticleWorld::new(10, 0);
+        let gravity = Vec3::new(0, -10, 0);
+        let sleeper = world.add_particle(Particle::from_position(Vec3::new(0, 0, 0), 1, Vec3::default(), gravity, 1));
+        let insomniac = world.add_particle(Particle::from_position(Vec3::new(5, 0, 0), 1, Vec3::default(), gravity, 1));
+        world.get_particle_mut(insomniac).set_can_sleep(false);
+        world.add_contact_generator(Rc::new(RefCell::new(ParticleHalfSpaceContacts::ground(0, 0, 0))));
+
+        for _ in 0..120 {
+            world.step(1.0 / 60.0);
+        }
+
+        assert!(!world.get_particle(sleeper).is_awake());
+        assert!(world.get_particle(insomniac).is_awake());
+        assert_eq!(world.get_particle(sleeper).get_velocity(), Vec3::default());
+
+        // Forces wake sleeping particles up
+        world.start_frame();
+        world.get_particle_mut(sleeper).add_force(Vec3::new(100, 0, 0));
+        assert!(world.get_particle(sleeper).is_awake());
+        world.run_physics(1.0 / 60.0);
+        assert!(world.get_particle(sleeper).get_velocity().x > 0 as Real);
+    }
+
+    #[test]
+    fn contact_wakes_sleeping_particle() {
+        let mut particles = vec![
+            Particle::from_position(Vec3::new(0, 0, 0), 1, Vec3::new(2, 0, 0), Vec3::default(), 1),
+            Particle::from_position(Vec3::new(1, 0, 0), 1, Vec3::default(), Vec3::default(), 1),
+        ];
+        particles[1].set_awake(false);
+        let mut contacts = vec![ParticleContact::new(0, Some(1), Vec3::new(-1, 0, 0), 0, 1)];
+
+        ParticleContactResolver::new(4).resolve_contacts(&mut contacts, &mut particles, 0.1);
+
+        assert!(particles[1].is_awake());
+        assert_eq!(particles[1].get_velocity(), Vec3::new(2, 0, 0));
+    }
+
+    #[test]
+    fn rigid_body_sleeps_and_wakes_on_contact() {
+        let mut body = sphere_body(Vec3::default(), Vec3::new(0.1, 0, 0));
+        body.set_rotation(Vec3::new(0, 0.1, 0));
+
+        for _ in 0..120 {
+            body.integrate(1.0 / 60.0);
+        }
+
+        assert!(!body.is_awake());
+        assert_eq!(body.get_rotation(), Vec3::default());
+
+        let mut bodies = vec![body, sphere_body(Vec3::new(-1.9, 0, 0), Vec3::new(1, 0, 0))];
+        let mut contacts = vec![
+            Contact::new([Some(1), Some(0)], Vec3::new(-0.95, 0, 0), Vec3::new(-1, 0, 0), 0.1, 0, 0)
+        ];
+        ContactResolver::new(4, 4).resolve_contacts(&mut contacts, &mut bodies, 0.01);
+
+        assert!(bodies[0].is_awake());
+        assert!(bodies[0].get_velocity().x > 0 as Real);
+
+        // Bodies that can not sleep are woken up
+        bodies[1].set_awake(false);
+        bodies[1].set_can_sleep(false);
+        assert!(bodies[1].is_awake());
+
+        // So are bodies pushed or turned by a force
+        bodies[0].set_awake(false);
+        bodies[0].add_force(Vec3::new(1, 0, 0));
+        assert!(bodies[0].is_awake());
+        bodies[0].set_awake(false);
+        bodies[0].add_torque(Vec3::new(0, 1, 0));
+        assert!(bodies[0].is_awake());
+    }
+
+    #[test]
+    fn bounding_sphere_encloses_pair() {
+        let one = BoundingSphere::new(Vec3::new(0, 0, 0), 1);
+        let two = BoundingSphere::new(Vec3::new(4, 0, 0), 1);
+        let both = BoundingSphere::enclose(&one, &two);
+
+        assert_vec3_near(both.get_centre(), Vec3::new(2, 0, 0));
+        assert!((both.get_radius() - 3.0).abs() < 1e-4);
+        assert!(!one.overlaps(&two));
+        assert!(both.overlaps(&one));
+
+        // A sphere inside another one grows nothing
+        let inner = BoundingSphere::new(Vec3::new(1, 0, 0), 0.5);
+        assert_eq!(BoundingSphere::enclose(&both, &inner), both);
+        assert_eq!(both.get_growth(&inner), 0 as Real);
+    }
+
+    #[test]
+    fn aabb_overlap_and_growth() {
+        let one = Aabb::new(Vec3::new(0, 0, 0), Vec3::new(1, 1, 1));
+        let two = Aabb::from_centre_and_half_size(Vec3::new(1.5, 0.5, 0.5), Vec3::new(0.5, 0.5, 0.5));
+        let both = Aabb::enclose(&one, &two);
+
+        assert!(one.overlaps(&two));
+        assert!(!one.overlaps(&Aabb::new(Vec3::new(1.1, 0, 0), Vec3::new(2, 1, 1))));
+        assert_eq!(both.get_max(), Vec3::new(2, 1, 1));
+        assert_eq!(both.get_size(), 2.0);
+        assert_eq!(one.get_growth(&two), 4.0);
+    }
+
+    fn sorted_pairs(contacts: &[PotentialContact]) -> Vec<[usize; 2]> {
+        let mut pairs: Vec<[usize; 2]> = contacts.iter().map(|contact| {
+            let [one, two] = contact.bodies;
+            [one.min(two), one.max(two)]
+        }).collect();
+        pairs.sort();
+        pairs
+    }
+
+    #[test]
+    fn bvh_finds_overlapping_pairs() {
+        let spheres: Vec<BoundingSphere> = (0..20)
+            .map(|i| BoundingSphere::new(Vec3::new((i * 7 % 10) as Real, (i % 3) as Real, 0), 0.8))
+            .collect();
+        let mut expected = Vec::new();
+
+        for i in 0..spheres.len() {
+            for j in i + 1..spheres.len() {
+                if spheres[i].overlaps(&spheres[j]) {
+                    expected.push([i, j]);
+                }
+            }
+        }
+
+        let mut root = BVHNode::new(0, spheres[0]);
+        for (i, sphere) in spheres.iter().enumerate().skip(1) {
+            root.insert(i, *sphere);
+        }
+
+        assert!(!expected.is_empty());
+        assert_eq!(sorted_pairs(&root.get_potential_contacts(1000)), expected);
+        assert_eq!(root.get_potential_contacts(2).len(), 2);
+
+        // Rebalancing keeps the same pairs in a shallower tree
+        root.rebalance();
+        assert_eq!(root.get_depth(), 6);
+        assert_eq!(sorted_pairs(&root.get_potential_contacts(1000)), expected);
+
+        // Removing an object removes its pairs
+        let root = root.remove(3).unwrap();
+        expected.retain(|pair| !pair.contains(&3));
+        assert_eq!(sorted_pairs(&root.get_potential_contacts(1000)), expected);
+
+        let leaf = BVHNode::new(7, spheres[7]);
+        assert!(leaf.clone().remove(7).is_none());
+        assert!(leaf.remove(8).is_some());
+    }
+
+    #[test]
+    fn broad_phases_agree() {
+        let boxes = |shift: Real| -> Vec<Aabb> {
+            (0..40).map(|i| {
+                let centre = Vec3::new((i * 7 % 13) as Real * 0.5 + shift, (i * 3 % 5) as Real * 0.5, (i % 2) as Real);
+                let size = if i % 10 == 0 { 2.0 } else { 0.3 };
+                Aabb::from_centre_and_half_size(centre, Vec3::new(size, size, size))
+            }).collect()
+        };
+        let mut phases: Vec<Box<dyn BroadPhase>> = vec![
+            Box::new(BruteForceBroadPhase::new()),
+            Box::new(BVHBroadPhase::new()),
+            Box::new(SpatialHashGrid::new(1)),
+            Box::new(SweepAndPrune::new(SweepAxis::All)),
+            Box::new(SweepAndPrune::new(SweepAxis::X)),
+        ];
+
+        // Grids and sorted axes are updated incrementally, so check they
+        // follow moves and removals
+        for volumes in [boxes(0.0), boxes(0.2), boxes(0.2)[..25].to_vec()] {
+            for phase in phases.iter_mut() {
+                phase.update(&volumes);
+            }
+
+            let expected = sorted_pairs(&phases[0].get_potential_contacts(usize::MAX));
+            assert!(!expected.is_empty());
+
+            for phase in phases.iter().skip(1) {
+                assert_eq!(sorted_pairs(&phase.get_potential_contacts(usize::MAX)), expected);
+            }
+
+            // Limited grids always keep the same, lowest pairs
+            let limited: Vec<[usize; 2]> = phases[2].get_potential_contacts(3).iter().map(|contact| contact.bodies).collect();
+            assert_eq!(limited, expected[..3]);
+        }
+    }
+
+    #[test]
+    fn grid_hashes_positions_into_cells() {
+        let mut grid = SpatialHashGrid::new(2);
+
+        assert_eq!(grid.get_cell(Vec3::new(0.5, -0.5, 4)), [0, -1, 2]);
+
+        grid.update(&[Aabb::new(Vec3::new(-1, 0.5, 0.5), Vec3::new(1, 1.5, 1.5))]);
+        assert_eq!(grid.get_cell_count(), 2);
+
+        grid.update(&[]);
+        assert_eq!(grid.get_cell_count(), 0);
+    }
+
+    #[test]
+    fn grid_keeps_oversized_objects_apart() {
+        let mut grid = SpatialHashGrid::new(1);
+        let mut brute = BruteForceBroadPhase::new();
+        let mut volumes = vec![
+            Aabb::new(Vec3::new(-500, -500, -500), Vec3::new(500, 500, 500)),
+            Aabb::from_centre_and_half_size(Vec3::new(0.5, 0.5, 0.5), Vec3::new(0.4, 0.4, 0.4)),
+            Aabb::from_centre_and_half_size(Vec3::new(0.9, 0.5, 0.5), Vec3::new(0.4, 0.4, 0.4)),
+            Aabb::from_centre_and_half_size(Vec3::new(900.5, 0.5, 0.5), Vec3::new(0.4, 0.4, 0.4)),
+            Aabb::new(Vec3::new(-600, -1, -1), Vec3::new(-400, 1, 1)),
+        ];
+
+        // The huge boxes are not hashed into the cells
+        grid.update(&volumes);
+        brute.update(&volumes);
+        assert_eq!(grid.get_cell_count(), 3);
+        assert_eq!(sorted_pairs(&grid.get_potential_contacts(usize::MAX)), sorted_pairs(&brute.get_potential_contacts(usize::MAX)));
+        assert_eq!(grid.query(&Aabb::from_centre_and_half_size(Vec3::new(10, 0, 0), Vec3::new(0.1, 0.1, 0.1))), vec![0]);
+
+        // Shrinking them puts them back into the cells
+        volumes[0] = Aabb::from_centre_and_half_size(Vec3::new(0.5, 0.5, 0.5), Vec3::new(1, 1, 1));
+        grid.update(&volumes);
+        brute.update(&volumes);
+        assert_eq!(sorted_pairs(&grid.get_potential_contacts(usize::MAX)), sorted_pairs(&brute.get_potential_contacts(usize::MAX)));
+    }
+
+    #[test]
+    fn collider_bounces_particles() {
+        let mut world = ParticleWorld::new(10, 0);
+        let first = world.add_particle(Particle::from_position(Vec3::new(0, 0, 0), 1, Vec3::new(1, 0, 0), Vec3::default(), 1));
+        let second = world.add_particle(Particle::from_position(Vec3::new(1.05, 0, 0), 1, Vec3::new(-1, 0, 0), Vec3::default(), 1));
+        world.add_particle(Particle::from_position(Vec3::new(10, 0, 0), 1, Vec3::default(), Vec3::default(), 1));
+        let grid: Rc<RefCell<dyn BroadPhase>> = Rc::new(RefCell::new(SpatialHashGrid::new(1)));
+        let collider = ParticleCollider::new(grid.clone(), 0.5, 1, 0);
+        assert!(Rc::ptr_eq(collider.get_broad_phase(), &grid));
+        world.add_contact_generator(Rc::new(RefCell::new(collider)));
+        world.set_broad_phase(grid);
+        world.set_particle_radius(0.5);
+
+        world.step(0.1);
+
+        assert_eq!(world.get_contacts().len(), 1);
+        assert_vec3_near(world.get_particle(first).get_velocity(), Vec3::new(-1, 0, 0));
+        assert_vec3_near(world.get_particle(second).get_velocity(), Vec3::new(1, 0, 0));
+
+        // The queries see the particles through the same grid
+        assert_eq!(world.overlap_sphere(Vec3::new(10, 0, 0), 0.1, ALL_LAYERS), vec![2]);
+    }
+
+    #[test]
+    fn sweep_and_prune_reports_pair_events() {
+        let at = |x: Real| Aabb::from_centre_and_half_size(Vec3::new(x, 0, 0), Vec3::new(0.5, 0.5, 0.5));
+        let mut sweep = SweepAndPrune::new(SweepAxis::All);
+
+        sweep.update(&[at(0.0), at(2.0)]);
+        assert!(sweep.get_events().is_empty());
+
+        sweep.update(&[at(0.0), at(0.8), at(5.0)]);
+        assert_eq!(sweep.get_events(), &[PairEvent::Added([0, 1])]);
+
+        // Nothing changes while the overlap lasts
+        sweep.update(&[at(0.1), at(0.8), at(5.0)]);
+        assert!(sweep.get_events().is_empty());
+        assert_eq!(sweep.get_potential_contacts(10), vec![PotentialContact { bodies: [0, 1] }]);
+
+        sweep.update(&[at(0.0), at(4.6), at(5.0)]);
+        assert_eq!(sweep.get_events(), &[PairEvent::Removed([0, 1]), PairEvent::Added([1, 2])]);
+
+        sweep.update(&[at(0.0), at(4.6)]);
+        assert_eq!(sweep.get_events(), &[PairEvent::Removed([1, 2])]);
+        assert_eq!(sweep.get_pair_count(), 0);
+    }
+
+    #[test]
+    fn raycast_primitives() {
+        let bodies = vec![RigidBody::default(); 2];
+        let ray = Ray::new(Vec3::new(-5, 0, 0), Vec3::new(2, 0, 0));
+
+        let mut sphere = CollisionSphere::new(Some(0), Matrix4::default(), 1);
+        sphere.primitive.calculate_internals(&bodies);
+        let hit = raycast_sphere(&ray, &sphere, 10).unwrap();
+        assert!((hit.distance - 4.0).abs() < 1e-4);
+        assert_vec3_near(hit.point, Vec3::new(-1, 0, 0));
+        assert_vec3_near(hit.normal, Vec3::new(-1, 0, 0));
+        assert_eq!(hit.body, Some(0));
+        assert!(raycast_sphere(&ray, &sphere, 3).is_none());
+        assert!(raycast_sphere(&Ray::new(Vec3::new(-5, 2, 0), Vec3::new(1, 0, 0)), &sphere, 10).is_none());
+        assert_eq!(raycast_sphere(&Ray::new(Vec3::default(), Vec3::new(0, 1, 0)), &sphere, 10).unwrap().distance, 0.0);
+
+        // A box turned a quarter turn around y shows its z face to the ray
+        let turn = Quaternion::from_axis_angle(Vec3::new(0, 1, 0), REAL_PI / 2.0);
+        let mut cube = CollisionBox::new(Some(1), placed(Vec3::default(), turn), Vec3::new(1, 1, 2));
+        cube.primitive.calculate_internals(&bodies);
+        let hit = raycast_box(&ray, &cube, 10).unwrap();
+        assert!((hit.distance - 3.0).abs() < 1e-4);
+        assert_vec3_near(hit.normal, Vec3::new(-1, 0, 0));
+
+        let aabb = Aabb::new(Vec3::new(-1, -1, -1), Vec3::new(1, 1, 1));
+        assert!((raycast_aabb(&ray, &aabb, 10).unwrap().distance - 4.0).abs() < 1e-4);
+
+        let ground = CollisionPlane::new(Vec3::new(0, 1, 0), -1);
+        let down = Ray::new(Vec3::new(0, 3, 0), Vec3::new(1, -1, 0));
+        let hit = raycast_plane(&down, &ground, 10).unwrap();
+        assert_vec3_near(hit.point, Vec3::new(4, -1, 0));
+        assert_vec3_near(hit.normal, Vec3::new(0, 1, 0));
+        assert!(raycast_plane(&ray, &ground, 10).is_none());
+
+        // A capsule standing along y is hit on its side and on its end
+        let mut capsule = CollisionCapsule::new(None, Matrix4::default(), 0.5, 1);
+        capsule.primitive.calculate_internals(&bodies);
+        let hit = raycast_capsule(&Ray::new(Vec3::new(-5, 0.5, 0), Vec3::new(1, 0, 0)), &capsule, 10).unwrap();
+        assert!((hit.distance - 4.5).abs() < 1e-4);
+        assert_vec3_near(hit.normal, Vec3::new(-1, 0, 0));
+        let hit = raycast_capsule(&Ray::new(Vec3::new(0, 5, 0), Vec3::new(0, -1, 0)), &capsule, 10).unwrap();
+        assert!((hit.distance - 3.5).abs() < 1e-4);
+        assert_vec3_near(hit.normal, Vec3::new(0, 1, 0));
+        assert!(raycast_capsule(&Ray::new(Vec3::new(-5, 2, 0), Vec3::new(1, 0, 0)), &capsule, 10).is_none());
+    }
+
+    #[test]
+    fn world_raycasts_particles() {
+        let mut world = ParticleWorld::new(10, 0);
+
+        for x in [3, 1, 8] {
+            world.add_particle(Particle::from_position(Vec3::new(x, 0, 0), 1, Vec3::default(), Vec3::default(), 1));
+        }
+        world.add_particle(Particle::from_position(Vec3::new(2, 5, 0), 1, Vec3::default(), Vec3::default(), 1));
+        world.set_broad_phase(Rc::new(RefCell::new(SpatialHashGrid::new(1))));
+        world.set_particle_radius(0.5);
+
+        let ray = Ray::new(Vec3::new(-1, 0, 0), Vec3::new(1, 0, 0));
+        let closest = world.raycast_closest(&ray, 20, ALL_LAYERS).unwrap();
+        assert_eq!(closest.body, Some(1));
+        assert!((closest.distance - 1.5).abs() < 1e-4);
+
+        let hits: Vec<Option<usize>> = world.raycast_all(&ray, 5, ALL_LAYERS).iter().map(|hit| hit.body).collect();
+        assert_eq!(hits, vec![Some(1), Some(0)]);
+
+        // Queries follow the particles as they move
+        world.get_particle_mut(3).set_velocity(Vec3::new(0, -50, 0));
+        world.step(0.1);
+        assert_eq!(world.raycast_all(&ray, 20, ALL_LAYERS).len(), 4);
+    }
+
+    #[test]
+    fn world_queries_follow_integration() {
+        let mut world = ParticleWorld::new(1, 0);
+        let index = world.add_particle(Particle::new(1, Vec3::new(100, 0, 0), Vec3::default(), 1));
+        world.set_particle_radius(0.5);
+        assert_eq!(world.point_inside(Vec3::default(), ALL_LAYERS), vec![index]);
+
+        world.integrate(1);
+        assert!(world.point_inside(Vec3::default(), ALL_LAYERS).is_empty());
+        assert_eq!(world.overlap_sphere(Vec3::new(100, 0, 0), 1, ALL_LAYERS), vec![index]);
+    }
+
+    #[test]
+    fn world_shape_queries_filter_layers() {
+        let mut world = ParticleWorld::new(10, 0);
+
+        for x in [0, 2, 6] {
+            world.add_particle(Particle::from_position(Vec3::new(x, 0, 0), 1, Vec3::default(), Vec3::default(), 1));
+        }
+        world.set_broad_phase(Rc::new(RefCell::new(SpatialHashGrid::new(1))));
+        world.set_particle_radius(0.5);
+        world.set_particle_layer(1, 2);
+        assert_eq!(world.get_particle_layer(0), DEFAULT_LAYER);
+
+        // A sphere swept along z, off the x axis, still touches particles
+        let ray = Ray::new(Vec3::new(2, 1, -5), Vec3::new(0, 0, 1));
+        assert!(world.raycast_closest(&ray, 10, ALL_LAYERS).is_none());
+        let hit = world.sphere_cast(&ray, 1, 10, ALL_LAYERS).unwrap();
+        assert_eq!(hit.body, Some(1));
+        assert!((hit.distance - (5.0 - (1.25 as Real).sqrt())).abs() < 1e-4);
+        assert!(((hit.point - Vec3::new(2, 0, 0)).magnitude() - 0.5).abs() < 1e-4);
+        assert!(world.sphere_cast(&ray, 1, 10, DEFAULT_LAYER).is_none());
+
+        assert_eq!(world.overlap_sphere(Vec3::new(1, 0, 0), 1, ALL_LAYERS), vec![0, 1]);
+        assert_eq!(world.overlap_sphere(Vec3::new(1, 0, 0), 1, DEFAULT_LAYER), vec![0]);
+        assert_eq!(world.overlap_sphere(Vec3::new(1, 0, 0), 5, ALL_LAYERS), vec![0, 1, 2]);
+
+        let volume = Aabb::new(Vec3::new(1.6, -1, -1), Vec3::new(7, 1, 1));
+        assert_eq!(world.overlap_aabb(&volume, ALL_LAYERS), vec![1, 2]);
+        assert_eq!(world.overlap_aabb(&volume, 2), vec![1]);
+
+        assert_eq!(world.point_inside(Vec3::new(6.2, 0.2, 0), ALL_LAYERS), vec![2]);
+        assert!(world.point_inside(Vec3::new(4, 0, 0), ALL_LAYERS).is_empty());
+    }
+
+    #[test]
+    fn static_particles_drop_forces() {
+        let mut particle = Particle::new_static(Vec3::default());
+        particle.add_force(Vec3::new(0, 10, 0));
+        particle.integrate(0.1);
+
+        assert_vec3_near(particle.get_force_accumulator(), Vec3::default());
+        assert_vec3_near(particle.get_position(), Vec3::default());
+    }
+
+    #[test]
+    fn integrate_uses_inverse_mass() {
+        let mut particle = Particle::new(4, Vec3::default(), Vec3::default(), 1);
+        particle.add_force(Vec3::new(8, 0, 0));
+        assert_eq!(particle.get_force_accumulator(), Vec3::new(8, 0, 0));
+
+        particle.integrate(1);
+
+        assert_eq!(particle.get_velocity(), Vec3::new(2, 0, 0));
+        assert_eq!(particle.get_force_accumulator(), Vec3::default());
+    }
+
+    #[test]
+    fn static_particle_does_not_move() {
+        let mut particle = Particle::new_static(Vec3::new(1, 2, 3));
+        particle.add_force(Vec3::new(100, 0, 0));
+        particle.integrate(1);
+
+        assert!(!particle.has_finite_mass());
+        assert_eq!(particle.get_mass(), REAL_MAX);
+        assert_eq!(particle.get_position(), Vec3::new(1, 2, 3));
+        assert_eq!(particle.get_velocity(), Vec3::default());
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_mass_panics() {
+        let mut particle = Particle::new(1, Vec3::default(), Vec3::default(), 1);
+        particle.set_mass(0);
+    }
+
+    fn simulate_projectile(integrator: Integrator, steps: u32, duration: Real) -> Particle {
+        let gravity = Vec3::new(0, -10, 0);
+        let mut particle = Particle::new(1, Vec3::new(3, 20, 0), gravity, 1);
+        particle.set_integrator(integrator);
+
+        for _ in 0..steps {
+            particle.integrate(duration);
+        }
+
+        particle
+    }
+
+    #[test]
+    fn exact_integrators_follow_projectile_motion() {
+        // x(t) = v0 t + g t^2 / 2, v(t) = v0 + g t
+        let time = 2.0;
+        let expected_position = Vec3::new(6, 20, 0);
+        let expected_velocity = Vec3::new(3, 0, 0);
+
+        for integrator in [Integrator::VelocityVerlet, Integrator::RungeKutta4] {
+            let particle = simulate_projectile(integrator, 100, time / 100.0);
+            assert!((particle.get_position() - expected_position).magnitude() < 1e-3);
+            assert!((particle.get_velocity() - expected_velocity).magnitude() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn euler_converges_to_projectile_motion() {
+        let coarse = simulate_projectile(Integrator::SemiImplicitEuler, 10, 0.2);
+        let fine = simulate_projectile(Integrator::SemiImplicitEuler, 1000, 0.002);
+        let expected_position = Vec3::new(6, 20, 0);
+
+        // The position error is proportional to the time step
+        let coarse_error = (coarse.get_position() - expected_position).magnitude();
+        let fine_error = (fine.get_position() - expected_position).magnitude();
+        assert!((coarse_error - 2.0).abs() < 1e-3);
+        assert!(fine_error < 0.021);
+        assert!((fine.get_velocity() - Vec3::new(3, 0, 0)).magnitude() < 1e-3);
+    }
+
+    #[test]
+    fn rk4_integrates_damping_as_drag() {
+        let mut particle = Particle::new(1, Vec3::new(10, 0, 0), Vec3::default(), 0.5);
+        particle.set_integrator(Integrator::RungeKutta4);
+
+        for _ in 0..10 {
+            particle.integrate(0.1);
+        }
+
+        // v(t) = v0 d^t, x(t) = v0 (d^t - 1) / ln(d)
+        let drag = (0.5 as Real).ln();
+        assert!((particle.get_velocity().x - 5.0).abs() < 1e-3);
+        assert!((particle.get_position().x - (-5.0 / drag)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn registry_applies_gravity() {
+        let mut particles = vec![Particle::new(2, Vec3::default(), Vec3::default(), 1)];
+        let gravity = Rc::new(RefCell::new(ParticleGravity::new(Vec3::new(0, -10, 0))));
+        let mut registry = ParticleForceRegistry::new();
+        registry.add(0, gravity);
+
+        registry.update_forces(&mut particles, 1);
+        particles[0].integrate(1);
+
+        assert_eq!(particles[0].get_velocity(), Vec3::new(0, -10, 0));
+    }
+
+    #[test]
+    fn anchored_spring_pulls_towards_anchor() {
+        let mut particles = vec![Particle::from_position(Vec3::new(3, 0, 0), 1, Vec3::default(), Vec3::default(), 1)];
+        let spring: Rc<RefCell<dyn ParticleForceGenerator>> =
+            Rc::new(RefCell::new(ParticleAnchoredSpring::new(Vec3::default(), 2, 1)));
+        let mut registry = ParticleForceRegistry::new();
+        registry.add(0, spring.clone());
+
+        registry.update_forces(&mut particles, 1);
+        particles[0].integrate(1);
+        assert_eq!(particles[0].get_velocity(), Vec3::new(-4, 0, 0));
+
+        registry.remove(0, &spring);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn world_runs_physics() {
+        let mut world = ParticleWorld::new(10, 0);
+        let index = world.add_particle(Particle::new(1, Vec3::new(1, 0, 0), Vec3::default(), 1));
+        let gravity = Rc::new(RefCell::new(ParticleGravity::new(Vec3::new(0, -10, 0))));
+        world.get_force_registry_mut().add(index, gravity);
+
+        world.start_frame();
+        world.run_physics(1);
+
+        assert_eq!(world.get_particle(index).get_position(), Vec3::new(1, -10, 0));
+        assert_eq!(world.get_particle(index).get_velocity(), Vec3::new(1, -10, 0));
+    }
+
+    #[test]
+    fn contact_bounces_particles_apart() {
+        let mut particles = vec![
+            Particle::from_position(Vec3::new(0, 0, 0), 1, Vec3::new(2, 0, 0), Vec3::default(), 1),
+            Particle::from_position(Vec3::new(1, 0, 0), 1, Vec3::new(-2, 0, 0), Vec3::default(), 1),
+        ];
+        let mut contacts = vec![ParticleContact::new(0, Some(1), Vec3::new(-1, 0, 0), 0.5, 1)];
+        let mut resolver = ParticleContactResolver::new(4);
+
+        resolver.resolve_contacts(&mut contacts, &mut particles, 0.1);
+
+        assert_eq!(particles[0].get_velocity(), Vec3::new(-2, 0, 0));
+        assert_eq!(particles[1].get_velocity(), Vec3::new(2, 0, 0));
+        assert_eq!(particles[0].get_position(), Vec3::new(-0.25, 0, 0));
+        assert_eq!(particles[1].get_position(), Vec3::new(1.25, 0, 0));
+        assert_eq!(resolver.get_iterations_used(), 1);
+    }
+
+    #[test]
+    fn contact_with_scenery_moves_only_the_particle() {
+        let mut particles = vec![Particle::from_position(Vec3::new(0, -0.5, 0), 2, Vec3::new(0, -4, 0), Vec3::default(), 1)];
+        let mut contacts = vec![ParticleContact::new(0, None, Vec3::new(0, 1, 0), 0.5, 0.5)];
+        let mut resolver = ParticleContactResolver::new(2);
+
+        resolver.resolve_contacts(&mut contacts, &mut particles, 0.1);
+
+        assert_eq!(particles[0].get_velocity(), Vec3::new(0, 2, 0));
+        assert_eq!(particles[0].get_position(), Vec3::default());
+    }
+
+    #[test]
+    fn rod_keeps_particles_at_its_length() {
+        let mut world = ParticleWorld::new(10, 0);
+        let first = world.add_particle(Particle::from_position(Vec3::new(0, 0, 0), 1, Vec3::new(-1, 0, 0), Vec3::default(), 1));
+        let second = world.add_particle(Particle::from_position(Vec3::new(2, 0, 0), 1, Vec3::new(1, 0, 0), Vec3::default(), 1));
+        world.add_contact_generator(Rc::new(RefCell::new(ParticleRod::new([first, second], 2))));
+
+        world.start_frame();
+        world.run_physics(0.5);
+
+        let distance = world.get_particle(second).get_position() - world.get_particle(first).get_position();
+        assert!((distance.magnitude() - 2.0).abs() < 1e-4);
+        assert!(world.get_particle(first).get_velocity().magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn slack_cable_generates_no_contact() {
+        let particles = vec![Particle::from_position(Vec3::new(0, -1, 0), 1, Vec3::default(), Vec3::default(), 1)];
+        let mut cable = ParticleCableConstraint::new(0, Vec3::default(), 2, 0.5);
+        let mut contacts = Vec::new();
+
+        assert_eq!(cable.add_contact(&particles, &mut contacts, 1), 0);
+        assert!(contacts.is_empty());
+    }
+
+    #[test]
+    fn ground_bounces_particle_with_radius() {
+        let mut world = ParticleWorld::new(10, 0);
+        let index = world.add_particle(Particle::from_position(Vec3::new(0, 0.5, 0), 1, Vec3::new(0, -2, 0), Vec3::default(), 1));
+        let mut ground = ParticleHalfSpaceContacts::ground(0, 0.5, 0);
+        ground.set_radius(1);
+        world.add_contact_generator(Rc::new(RefCell::new(ground)));
+
+        world.start_frame();
+        world.run_physics(0.25);
+
+        assert_eq!(world.get_particle(index).get_position(), Vec3::new(0, 1, 0));
+        assert_eq!(world.get_particle(index).get_velocity(), Vec3::new(0, 1, 0));
+    }
+
+    #[test]
+    fn ground_friction_slows_sliding_particle() {
+        let mut particles = vec![Particle::from_position(Vec3::new(0, -0.1, 0), 1, Vec3::new(3, -1, 0), Vec3::default(), 1)];
+        let mut ground = ParticleHalfSpaceContacts::ground(0, 0, 0.5);
+        let mut contacts = Vec::new();
+        ground.add_contact(&particles, &mut contacts, 1);
+
+        ParticleContactResolver::new(1).resolve_contacts(&mut contacts, &mut particles, 0.1);
+
+        assert_eq!(particles[0].get_velocity(), Vec3::new(2.5, 0, 0));
+    }
+
+    #[test]
+    fn particle_comes_to_rest_on_ground() {
+        let mut world = ParticleWorld::new(10, 0);
+        let index = world.add_particle(Particle::new(1, Vec3::default(), Vec3::default(), 1));
+        let gravity = Rc::new(RefCell::new(ParticleGravity::new(Vec3::new(0, -10, 0))));
+        world.get_force_registry_mut().add(index, gravity);
+        world.add_contact_generator(Rc::new(RefCell::new(ParticleHalfSpaceContacts::ground(0, 0.8, 0))));
+
+        for _ in 0..120 {
+            world.start_frame();
+            world.run_physics(1.0 / 60.0);
+        }
+
+        let particle = world.get_particle(index);
+        assert!(particle.get_velocity().magnitude() < 1e-4);
+        assert!(particle.get_position().y.abs() < 1e-2);
+    }
+
+    #[test]
+    fn clock_runs_fixed_steps() {
+        let mut clock = SimulationClock::new(0.25, 4);
+        let mut durations = Vec::new();
+
+        assert_eq!(clock.advance(0.6, |duration| durations.push(duration)), 2);
+        assert_eq!(durations, vec![0.25, 0.25]);
+        assert!((clock.alpha() - 0.4).abs() < 1e-4);
+
+        // Hitches are capped and the backlog is dropped
+        assert_eq!(clock.advance(10, |_| ()), 4);
+        assert!(clock.alpha() < 1.0);
+        assert_eq!(clock.get_time(), 1.5);
+    }
+
+    #[test]
+    fn world_interpolates_between_steps() {
+        let mut world = ParticleWorld::new(1, 0);
+        let index = world.add_particle(Particle::new(1, Vec3::new(4, 0, 0), Vec3::default(), 1));
+        let mut clock = SimulationClock::new(0.5, 4);
+
+        assert_eq!(world.advance(&mut clock, 0.75), 1);
+
+        let state = world.get_interpolated_state(index, clock.alpha());
+        assert_eq!(world.get_previous_state(index).position, Vec3::default());
+        assert_eq!(state.position, Vec3::new(1, 0, 0));
+        assert_eq!(state.velocity, Vec3::new(4, 0, 0));
+
+        // Running the physics by hand records the states as well
+        world.start_frame();
+        world.run_physics(0.5);
+        assert_eq!(world.get_previous_state(index).position, Vec3::new(2, 0, 0));
+    }
+}
+
+pub mod precision {
+    /// Defines the real number precision.
+    /// It can be f32 or f64, simple and double
+    /// precisions repectively. Double precision is
+    /// selected with the `double-precision` feature.
+    #[cfg(not(feature = "double-precision"))]
+    pub type Real = f32;
+
+    /// Defines the real number precision.
+    /// It can be f32 or f64, simple and double
+    /// precisions repectively. Double precision is
+    /// selected with the `double-precision` feature.
+    #[cfg(feature = "double-precision")]
+    pub type Real = f64;
+
+    /// The difference between one and the next larger real number.
+    pub const REAL_EPSILON: Real = Real::EPSILON;
+
+    /// The largest finite real number.
+    pub const REAL_MAX: Real = Real::MAX;
+
+    /// Archimedes' constant in the real precision.
+    pub const REAL_PI: Real = std::f64::consts::PI as Real;
+
+    /// Trait for values that can be converted to the real type
+    pub trait AsReal {
+        fn as_real(self) -> Real;
+    }
+
+    impl AsReal for i8 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+
+    impl AsReal for i16 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+
+    impl AsReal for i32 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+
+    impl AsReal for i64 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+
+    impl AsReal for i128 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+
+    impl AsReal for u8 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+
+    impl AsReal for u16 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+
+    impl AsReal for u32 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+
+    impl AsReal for u64 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+
+    impl AsReal for u128 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+
+    impl AsReal for f32 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+
+    impl AsReal for f64 {
+        fn as_real(self) -> Real {
+            self as Real
+        }
+    }
+}
+
+pub mod core {
+    use std::ops::*;
+    use super::precision::*;
+    use rand::Rng;
+
+    #[allow(unused)]
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    /// Tri-dimensional vector composed of three elements
+    /// for the x, y and z axes.
+    pub struct Vec3 {
+        pub x: Real,
+        pub y: Real,
+        pub z: Real,
+        pad: Real
+    }
+
+    impl Default for Vec3 {
+        /// Returns a new Vector with all elements set to zero.
+        fn default() -> Self {
+            Self::new(0., 0., 0.)
+        }
+    }
+
+    impl Vec3 {
+        /// Creates a new Vec3, defining the the values for all axes
+        ///
+        /// # Arguments
+        /// * `x` - value for the x axis
+        /// * `y` - value for the y axis
+        /// * `z` - value for the z axis
+        ///
+        /// # Examples
+        /// ```
+        /// use rusty_cyclone::core::Vec3;
+        /// let vec = Vec3::new(-1, 3.98, 4);
+        /// ```
+        pub fn new<T1, T2, T3>(x: T1, y: T2, z: T3) -> Self
+        where T1: AsReal, T2: AsReal, T3: AsReal {
+            Self {
+                x: x.as_real(),
+                y: y.as_real(),
+                z: z.as_real(),
+                pad: 0 as Real
+            }
+        }
+
+        /// Generates a vector with random elements from a given range.
+        ///
+        /// ### Arguments
+        /// * `range` - The range of which element will by randomly assigned.
+        pub fn from_range(range: Range<i32>) -> Self {
+            let mut rng = rand::thread_rng();
+
+            let range1 = rng.gen_range(range.clone());
+            let range2 = rng.gen_range(range.clone());
+            let range3 = rng.gen_range(range);
+
+            Self::new(range1, range2, range3)
+        }
+    }
+
+    impl Add<Vec3> for Vec3 {
+        type Output = Self;
+
+        fn add(self, other: Vec3) -> Self::Output {
+            Self::new(
+                self.x + other.x,
+                self.y + other.y,
+                self.z + other.z
+            )
+        }
+    }
+
+    impl<T: AsReal> Add<T> for Vec3 {
+        type Output = Self;
+
+        fn add(self, other: T) -> Self::Output {
+            let other = other.as_real();
+
+            Self::new(
+                self.x + other,
+                self.y + other,
+                self.z + other
+            )
+        }
+    }
+
+    impl AddAssign<Vec3> for Vec3{
+        fn add_assign(&mut self, other: Vec3) {
+            *self = Self::new(
+                self.x + other.x,
+                self.y + other.y,
+                self.z + other.z
+            );
+        }
+    }
+
+    impl<T: AsReal> AddAssign<T> for Vec3 {
+        fn add_assign(&mut self, other: T) {
+            let other = other.as_real();
+
+            *self = Self::new(
+                self.x + other,
+                self.y + other,
+                self.z + other,
+            )
+        }
+    }
+
+    impl Sub<Vec3> for Vec3 {
+        type Output = Self;
+
+        fn sub(self, other: Vec3) -> Self::Output {
+            Self::new(
+                self.x - other.x,
+                self.y - other.y,
+                self.z - other.z,
+            )
+        }
+    }
+
+    impl<T: AsReal> Sub<T> for Vec3 {
+        type Output = Self;
+
+        fn sub(self, other: T) -> Self::Output {
+            let other = other.as_real();
+
+            Self::new(
+                self.x - other,
+                self.y - other,
+                self.z - other,
+            )
+        }
+    }
+
+    impl SubAssign<Vec3> for Vec3 {
+        fn sub_assign(&mut self, other: Vec3) {
+            *self = Self::new(
+                self.x - other.x,
+                self.y - other.y,
+                self.z - other.z
+            );
+        }
+    }
+
+    impl<T: AsReal> SubAssign<T> for Vec3 {
+        fn sub_assign(&mut self, other: T) {
+            let other = other.as_real();
+
+            *self = Self::new(
+                self.x - other,
+                self.y - other,
+                self.z - other
+            );
+        }
+    }
+
+    impl<T: AsReal> Div<T> for Vec3 {
+        type Output = Self;
+
+        fn div(self, other: T) -> Self {
+            let other = other.as_real();
+
+            if other.as_real() == 0 as Real {
+                panic!("Division by zero!");
+            }
+
+            Self::new(
+                self.x / other,
+                self.y / other,
+                self.z / other
+            )
+        }
+    }
+
+    impl<T: AsReal> DivAssign<T> for Vec3 {
+        fn div_assign(&mut self, other: T) {
+            let other = other.as_real();
+
+            if other == 0 as Real {
+                panic!("Division by zero!");
+            }
+
+            *self = Self::new(
+                self.x / other,
                 self.y / other,
                 self.z / other
             );
         }
-    }
+    }
+
+    impl<T: AsReal> Mul<T> for Vec3 {
+        type Output = Self;
+
+        fn mul(self, other: T) -> Self {
+            let other = other.as_real();
+
+            Self::new(
+                self.x * other,
+                self.y * other,
+                self.z * other
+            )
+        }
+    }
+
+    impl<T: AsReal> MulAssign<T> for Vec3 {
+        fn mul_assign(&mut self, other: T) {
+            let other = other.as_real();
+
+            *self = Self::new(
+                self.x * other,
+                self.y * other,
+                self.z * other
+            )
+        }
+    }
+
+    impl Neg for Vec3 {
+        type Output = Self;
+
+        fn neg(self) -> Self::Output {
+            Vec3::new(-self.x, -self.y, -self.z)
+        }
+    }
+
+    impl Vec3 {
+        /// Invert all the elements of the vector
+        pub fn invert(&mut self) {
+            *self = -(*self);
+        }
+
+        /// Returns the magnitude of the vector
+        pub fn magnitude(&self) -> Real {
+            (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
+        }
+
+        /// Returns the square of the magnitude of the vector
+        pub fn square_magnitude(&self) -> Real {
+            self.x.powi(2) + self.y.powi(2) + self.z.powi(2)
+        }
+
+        /// Normalize the elements of the vector
+        pub fn normalize(&mut self) {
+            let m = self.magnitude();
+
+            if m > 0.0 {
+                *self *= 1.0 / m;
+            }
+        }
+
+        /// Adds elements of the vector to the scaled correspondend element of
+        /// another vector, given as arguments.
+        ///
+        /// ### Arguments
+        /// * `other` - The vector to be scaled and added
+        /// * `scale` - The scale factor
+        pub fn add_scaled_vector<T: AsReal>(&mut self, other: Vec3, scale: T) {
+            *self += other * scale.as_real();
+        }
+
+        /// Returns the element-wise multiplication of this vector
+        /// and the argument vector.
+        pub fn elementwise_prod(&self, other: Vec3) -> Vec3 {
+            Vec3::new(
+                self.x * other.x,
+                self.y * other.y,
+                self.z * other.z,
+            )
+        }
+
+        /// Element-wise operation between this vector and the
+        /// given argument vector, the result is then replaced
+        /// to the repective elements of this vector.
+        pub fn elementwise_prod_assign(&mut self, other: Vec3) {
+            *self = self.elementwise_prod(other);
+        }
+
+        /// Returns the scalar product between this vector
+        /// and the one give as argument.
+        pub fn dot(&self, other: Vec3) -> Real {
+            self.x * other.x + self.y * other.y + self.z * other.z
+        }
+
+        /// Returns the vectorial product between this vector
+        /// and the given one.
+        pub fn vec_prod(&self, other: Vec3) -> Vec3 {
+            Vec3::new(
+                self.y * other.z - self.z * other.y,
+                self.z * other.x - self.x * other.z,
+                self.x * other.y - self.y * other.x
+            )
+        }
+
+        /// Calculates the vectorial product between this vector
+        /// and the one given as argument, and then replaces the
+        /// elements of this vector to the result of the product.
+        pub fn vec_prod_assign(&mut self, other: Vec3) {
+            *self = self.vec_prod(other);
+        }
+    }
+
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    /// Holds a three degree of freedom orientation.
+    ///
+    /// Quaternions have several mathematical properties that make them
+    /// useful for representing orientations, but require four items of
+    /// data to hold the three degrees of freedom. These four items of
+    /// data can be viewed as the coefficients of a complex number with
+    /// three imaginary parts. To represent an orientation the quaternion
+    /// must have unit length.
+    pub struct Quaternion {
+        /// Holds the real component of the quaternion.
+        pub r: Real,
+        /// Holds the first complex component of the quaternion.
+        pub i: Real,
+        /// Holds the second complex component of the quaternion.
+        pub j: Real,
+        /// Holds the third complex component of the quaternion.
+        pub k: Real,
+    }
+
+    impl Default for Quaternion {
+        /// Returns the identity quaternion, representing no rotation.
+        fn default() -> Self {
+            Self::new(1, 0, 0, 0)
+        }
+    }
+
+    impl Quaternion {
+        /// Creates a new quaternion with the given components. The
+        /// quaternion is not normalized, so to represent an orientation
+        /// the components must describe a unit quaternion.
+        ///
+        /// # Arguments
+        /// * `r` - the real component
+        /// * `i` - the first complex component
+        /// * `j` - the second complex component
+        /// * `k` - the third complex component
+        ///
+        /// # Examples
+        /// ```
+        /// use rusty_cyclone::core::Quaternion;
+        /// let q = Quaternion::new(1, 0, 0.5, 0);
+        /// ```
+        pub fn new<T1, T2, T3, T4>(r: T1, i: T2, j: T3, k: T4) -> Self
+        where T1: AsReal, T2: AsReal, T3: AsReal, T4: AsReal {
+            Self {
+                r: r.as_real(),
+                i: i.as_real(),
+                j: j.as_real(),
+                k: k.as_real(),
+            }
+        }
+
+        /// Creates a quaternion representing a rotation of the given
+        /// angle (in radians) around the given axis.
+        pub fn from_axis_angle<T: AsReal>(axis: Vec3, angle: T) -> Self {
+            let half_angle = angle.as_real() * 0.5;
+            let mut axis = axis;
+            axis.normalize();
+
+            let sin = half_angle.sin();
+            Self::new(half_angle.cos(), axis.x * sin, axis.y * sin, axis.z * sin)
+        }
+
+        /// Returns the axis and angle (in radians) of the rotation
+        /// represented by this quaternion. For no rotation, the axis
+        /// is the x axis.
+        pub fn to_axis_angle(&self) -> (Vec3, Real) {
+            let mut q = *self;
+            q.normalize();
+
+            let angle = 2.0 * q.r.clamp(-1.0, 1.0).acos();
+            let sin = (1.0 - q.r * q.r).max(0.0).sqrt();
+
+            if sin < REAL_EPSILON.sqrt() {
+                return (Vec3::new(1, 0, 0), angle);
+            }
+
+            (Vec3::new(q.i / sin, q.j / sin, q.k / sin), angle)
+        }
+
+        /// Creates a quaternion from Euler angles (in radians), applied
+        /// as a rotation around the z axis (yaw), then around the y axis
+        /// (pitch) and last around the x axis (roll).
+        pub fn from_euler<T1, T2, T3>(roll: T1, pitch: T2, yaw: T3) -> Self
+        where T1: AsReal, T2: AsReal, T3: AsReal {
+            let (sr, cr) = (roll.as_real() * 0.5).sin_cos();
+            let (sp, cp) = (pitch.as_real() * 0.5).sin_cos();
+            let (sy, cy) = (yaw.as_real() * 0.5).sin_cos();
+
+            Self::new(
+                cr * cp * cy + sr * sp * sy,
+                sr * cp * cy - cr * sp * sy,
+                cr * sp * cy + sr * cp * sy,
+                cr * cp * sy - sr * sp * cy
+            )
+        }
+
+        /// Returns the Euler angles (in radians) of the rotation, as a
+        /// vector holding the roll, pitch and yaw in its x, y and z
+        /// elements. See `from_euler` for the order of the rotations.
+        pub fn to_euler(&self) -> Vec3 {
+            let mut q = *self;
+            q.normalize();
+
+            let roll = (2.0 * (q.r * q.i + q.j * q.k))
+                .atan2(1.0 - 2.0 * (q.i * q.i + q.j * q.j));
+            let pitch = (2.0 * (q.r * q.j - q.k * q.i)).clamp(-1.0, 1.0).asin();
+            let yaw = (2.0 * (q.r * q.k + q.i * q.j))
+                .atan2(1.0 - 2.0 * (q.j * q.j + q.k * q.k));
+
+            Vec3::new(roll, pitch, yaw)
+        }
+
+        /// Returns the magnitude of the quaternion.
+        pub fn magnitude(&self) -> Real {
+            self.dot(*self).sqrt()
+        }
+
+        /// Returns the scalar product between this quaternion and
+        /// the one given as argument.
+        pub fn dot(&self, other: Quaternion) -> Real {
+            self.r * other.r + self.i * other.i + self.j * other.j + self.k * other.k
+        }
+
+        /// Returns the conjugate of the quaternion, which is the
+        /// inverse rotation for unit quaternions.
+        pub fn conjugate(&self) -> Quaternion {
+            Quaternion::new(self.r, -self.i, -self.j, -self.k)
+        }
+
+        /// Normalises the quaternion to unit length, making it a valid
+        /// orientation quaternion. A zero length quaternion becomes the
+        /// identity.
+        pub fn normalize(&mut self) {
+            let d = self.dot(*self);
+
+            // Check for zero length quaternion, and use the no-rotation
+            // quaternion in that case.
+            if d < REAL_EPSILON {
+                *self = Quaternion::default();
+                return;
+            }
+
+            let d = 1.0 / d.sqrt();
+            *self = Quaternion::new(self.r * d, self.i * d, self.j * d, self.k * d);
+        }
+
+        /// Rotates the quaternion by a vector, multiplying it by the
+        /// pure quaternion built from the vector.
+        pub fn rotate_by_vector(&mut self, vector: Vec3) {
+            *self *= Quaternion::new(0, vector.x, vector.y, vector.z);
+        }
+
+        /// Adds the given vector to this, scaled by the given amount.
+        /// This is used to update the orientation quaternion by a
+        /// rotation and time, such as an angular velocity.
+        ///
+        /// ### Arguments
+        /// * `vector` - The vector to add
+        /// * `scale` - The amount of the vector to add
+        pub fn add_scaled_vector<T: AsReal>(&mut self, vector: Vec3, scale: T) {
+            let scale = scale.as_real();
+            let mut q = Quaternion::new(0, vector.x * scale, vector.y * scale, vector.z * scale);
+            q *= *self;
+
+            self.r += q.r * 0.5;
+            self.i += q.i * 0.5;
+            self.j += q.j * 0.5;
+            self.k += q.k * 0.5;
+        }
+
+        /// Returns the given vector rotated by this quaternion, which
+        /// must have unit length.
+        pub fn rotate(&self, vector: Vec3) -> Vec3 {
+            let q = *self * Quaternion::new(0, vector.x, vector.y, vector.z) * self.conjugate();
+            Vec3::new(q.i, q.j, q.k)
+        }
+
+        /// Spherical linear interpolation between this quaternion (at
+        /// `t` zero) and the given one (at `t` one), always following
+        /// the shortest path. Both quaternions must have unit length.
+        pub fn slerp<T: AsReal>(&self, other: Quaternion, t: T) -> Quaternion {
+            let t = t.as_real();
+            let mut other = other;
+            let mut cos = self.dot(other);
+
+            // Take the shortest path around the sphere
+            if cos < 0 as Real {
+                other = -other;
+                cos = -cos;
+            }
+
+            // Fall back to linear interpolation for very close orientations
+            let (a, b) = if cos > 1.0 - 1.0e-4 {
+                (1.0 - t, t)
+            } else {
+                let angle = cos.acos();
+                let sin = angle.sin();
+                (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+            };
+
+            let mut result = Quaternion::new(
+                self.r * a + other.r * b,
+                self.i * a + other.i * b,
+                self.j * a + other.j * b,
+                self.k * a + other.k * b
+            );
+            result.normalize();
+            result
+        }
+    }
+
+    impl Mul<Quaternion> for Quaternion {
+        type Output = Self;
+
+        /// Multiplies the quaternion by the given quaternion. The
+        /// resulting rotation applies `other` first and then this one.
+        fn mul(self, other: Quaternion) -> Self::Output {
+            Self::new(
+                self.r * other.r - self.i * other.i - self.j * other.j - self.k * other.k,
+                self.r * other.i + self.i * other.r + self.j * other.k - self.k * other.j,
+                self.r * other.j + self.j * other.r + self.k * other.i - self.i * other.k,
+                self.r * other.k + self.k * other.r + self.i * other.j - self.j * other.i
+            )
+        }
+    }
+
+    impl MulAssign<Quaternion> for Quaternion {
+        fn mul_assign(&mut self, other: Quaternion) {
+            *self = *self * other;
+        }
+    }
+
+    impl Neg for Quaternion {
+        type Output = Self;
+
+        fn neg(self) -> Self::Output {
+            Quaternion::new(-self.r, -self.i, -self.j, -self.k)
+        }
+    }
+
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    /// Holds an inertia tensor, consisting of a 3x3 row-major matrix.
+    /// This matrix is not padding to produce an aligned structure, since
+    /// it is most commonly used with a mass (single real) and two
+    /// damping coefficients to make the 12-element characteristics array
+    /// of a rigid body.
+    pub struct Matrix3 {
+        /// Holds the tensor matrix data in array form.
+        pub data: [Real; 9],
+    }
+
+    impl Default for Matrix3 {
+        /// Returns the identity matrix.
+        fn default() -> Self {
+            Self::from_diagonal(1, 1, 1)
+        }
+    }
+
+    impl Matrix3 {
+        /// Creates a new matrix from its elements, in row-major order.
+        pub fn new(data: [Real; 9]) -> Self {
+            Self { data }
+        }
+
+        /// Returns a matrix with all elements set to zero.
+        pub fn zero() -> Self {
+            Self::new([0 as Real; 9])
+        }
+
+        /// Creates a diagonal matrix with the given diagonal elements.
+        pub fn from_diagonal<T1, T2, T3>(a: T1, b: T2, c: T3) -> Self
+        where T1: AsReal, T2: AsReal, T3: AsReal {
+            let mut m = Self::zero();
+            m.data[0] = a.as_real();
+            m.data[4] = b.as_real();
+            m.data[8] = c.as_real();
+            m
+        }
+
+        /// Creates a matrix whose columns are the given vectors.
+        pub fn from_components(one: Vec3, two: Vec3, three: Vec3) -> Self {
+            Self::new([
+                one.x, two.x, three.x,
+                one.y, two.y, three.y,
+                one.z, two.z, three.z,
+            ])
+        }
+
+        /// Creates the skew symmetric matrix of the given vector, which
+        /// gives the same result as the vector product when multiplied
+        /// by a vector: `from_skew_symmetric(a) * b == a.vec_prod(b)`.
+        pub fn from_skew_symmetric(vector: Vec3) -> Self {
+            Self::new([
+                0 as Real, -vector.z, vector.y,
+                vector.z, 0 as Real, -vector.x,
+                -vector.y, vector.x, 0 as Real,
+            ])
+        }
+
+        /// Creates the rotation matrix of the given orientation, which
+        /// must be a unit quaternion.
+        pub fn from_quaternion(q: Quaternion) -> Self {
+            Self::new([
+                1.0 - (2.0 * q.j * q.j + 2.0 * q.k * q.k),
+                2.0 * q.i * q.j - 2.0 * q.k * q.r,
+                2.0 * q.i * q.k + 2.0 * q.j * q.r,
+                2.0 * q.i * q.j + 2.0 * q.k * q.r,
+                1.0 - (2.0 * q.i * q.i + 2.0 * q.k * q.k),
+                2.0 * q.j * q.k - 2.0 * q.i * q.r,
+                2.0 * q.i * q.k - 2.0 * q.j * q.r,
+                2.0 * q.j * q.k + 2.0 * q.i * q.r,
+                1.0 - (2.0 * q.i * q.i + 2.0 * q.j * q.j),
+            ])
+        }
+
+        /// Returns a matrix which is the linear interpolation of the two
+        /// given matrices by the given proportion.
+        pub fn linear_interpolate<T: AsReal>(a: Matrix3, b: Matrix3, prop: T) -> Self {
+            let prop = prop.as_real();
+            let omp = 1.0 - prop;
+            let mut result = Self::zero();
+
+            for i in 0..9 {
+                result.data[i] = a.data[i] * omp + b.data[i] * prop;
+            }
+
+            result
+        }
+
+        /// Returns the vector given by the column of the matrix at
+        /// the given index.
+        pub fn get_axis_vector(&self, index: usize) -> Vec3 {
+            Vec3::new(self.data[index], self.data[index + 3], self.data[index + 6])
+        }
+
+        /// Transforms the given vector by this matrix.
+        pub fn transform(&self, vector: Vec3) -> Vec3 {
+            *self * vector
+        }
+
+        /// Transforms the given vector by the transpose of this matrix.
+        pub fn transform_transpose(&self, vector: Vec3) -> Vec3 {
+            Vec3::new(
+                vector.x * self.data[0] + vector.y * self.data[3] + vector.z * self.data[6],
+                vector.x * self.data[1] + vector.y * self.data[4] + vector.z * self.data[7],
+                vector.x * self.data[2] + vector.y * self.data[5] + vector.z * self.data[8]
+            )
+        }
+
+        /// Returns the determinant of the matrix.
+        pub fn determinant(&self) -> Real {
+            let d = &self.data;
+            d[0] * (d[4] * d[8] - d[5] * d[7])
+                - d[1] * (d[3] * d[8] - d[5] * d[6])
+                + d[2] * (d[3] * d[7] - d[4] * d[6])
+        }
+
+        /// Returns the inverse of the matrix, or `None` if the matrix
+        /// is singular.
+        pub fn inverse(&self) -> Option<Matrix3> {
+            let det = self.determinant();
+
+            // Make sure the determinant is non-zero.
+            if det == 0 as Real {
+                return None;
+            }
+
+            let d = &self.data;
+            let inv_det = 1.0 / det;
+
+            Some(Self::new([
+                (d[4] * d[8] - d[5] * d[7]) * inv_det,
+                -(d[1] * d[8] - d[2] * d[7]) * inv_det,
+                (d[1] * d[5] - d[2] * d[4]) * inv_det,
+                -(d[3] * d[8] - d[5] * d[6]) * inv_det,
+                (d[0] * d[8] - d[2] * d[6]) * inv_det,
+                -(d[0] * d[5] - d[2] * d[3]) * inv_det,
+                (d[3] * d[7] - d[4] * d[6]) * inv_det,
+                -(d[0] * d[7] - d[1] * d[6]) * inv_det,
+                (d[0] * d[4] - d[1] * d[3]) * inv_det,
+            ]))
+        }
+
+        /// Returns the transpose of the matrix.
+        pub fn transpose(&self) -> Matrix3 {
+            let d = &self.data;
+            Self::new([
+                d[0], d[3], d[6],
+                d[1], d[4], d[7],
+                d[2], d[5], d[8],
+            ])
+        }
+    }
+
+    impl Mul<Vec3> for Matrix3 {
+        type Output = Vec3;
+
+        /// Transforms the given vector by this matrix.
+        fn mul(self, vector: Vec3) -> Self::Output {
+            Vec3::new(
+                vector.x * self.data[0] + vector.y * self.data[1] + vector.z * self.data[2],
+                vector.x * self.data[3] + vector.y * self.data[4] + vector.z * self.data[5],
+                vector.x * self.data[6] + vector.y * self.data[7] + vector.z * self.data[8]
+            )
+        }
+    }
+
+    impl Mul<Matrix3> for Matrix3 {
+        type Output = Self;
+
+        fn mul(self, other: Matrix3) -> Self::Output {
+            let mut result = Self::zero();
+
+            for row in 0..3 {
+                for col in 0..3 {
+                    result.data[row * 3 + col] = (0..3)
+                        .map(|i| self.data[row * 3 + i] * other.data[i * 3 + col])
+                        .sum();
+                }
+            }
+
+            result
+        }
+    }
+
+    impl MulAssign<Matrix3> for Matrix3 {
+        fn mul_assign(&mut self, other: Matrix3) {
+            *self = *self * other;
+        }
+    }
+
+    impl<T: AsReal> Mul<T> for Matrix3 {
+        type Output = Self;
+
+        fn mul(self, other: T) -> Self::Output {
+            let other = other.as_real();
+            Self::new(self.data.map(|element| element * other))
+        }
+    }
+
+    impl<T: AsReal> MulAssign<T> for Matrix3 {
+        fn mul_assign(&mut self, other: T) {
+            *self = *self * other;
+        }
+    }
+
+    impl Add<Matrix3> for Matrix3 {
+        type Output = Self;
+
+        fn add(self, other: Matrix3) -> Self::Output {
+            let mut result = self;
+            result += other;
+            result
+        }
+    }
+
+    impl AddAssign<Matrix3> for Matrix3 {
+        fn add_assign(&mut self, other: Matrix3) {
+            for i in 0..9 {
+                self.data[i] += other.data[i];
+            }
+        }
+    }
+
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    /// Holds a transform matrix, consisting of a rotation matrix and
+    /// a position. The matrix has 12 elements, it is assumed that the
+    /// remaining four are (0,0,0,1); producing a homogenous matrix.
+    pub struct Matrix4 {
+        /// Holds the transform matrix data in array form.
+        pub data: [Real; 12],
+    }
+
+    impl Default for Matrix4 {
+        /// Returns the identity transform.
+        fn default() -> Self {
+            Self::new([
+                1 as Real, 0 as Real, 0 as Real, 0 as Real,
+                0 as Real, 1 as Real, 0 as Real, 0 as Real,
+                0 as Real, 0 as Real, 1 as Real, 0 as Real,
+            ])
+        }
+    }
+
+    impl Matrix4 {
+        /// Creates a new transform from its elements, in row-major order.
+        pub fn new(data: [Real; 12]) -> Self {
+            Self { data }
+        }
+
+        /// Creates the transform of the given orientation, which must
+        /// be a unit quaternion, followed by the given translation.
+        pub fn from_orientation_and_pos(q: Quaternion, pos: Vec3) -> Self {
+            let r = Matrix3::from_quaternion(q).data;
+
+            Self::new([
+                r[0], r[1], r[2], pos.x,
+                r[3], r[4], r[5], pos.y,
+                r[6], r[7], r[8], pos.z,
+            ])
+        }
+
+        /// Returns the vector given by the column of the matrix at the
+        /// given index. The fourth column (index three) is the position.
+        pub fn get_axis_vector(&self, index: usize) -> Vec3 {
+            Vec3::new(self.data[index], self.data[index + 4], self.data[index + 8])
+        }
+
+        /// Returns the rotation part of the transform.
+        pub fn get_rotation(&self) -> Matrix3 {
+            let d = &self.data;
+            Matrix3::new([
+                d[0], d[1], d[2],
+                d[4], d[5], d[6],
+                d[8], d[9], d[10],
+            ])
+        }
+
+        /// Returns the determinant of the matrix.
+        pub fn determinant(&self) -> Real {
+            self.get_rotation().determinant()
+        }
+
+        /// Returns the inverse of the transform, or `None` if the matrix
+        /// is singular.
+        pub fn inverse(&self) -> Option<Matrix4> {
+            let rotation = self.get_rotation().inverse()?;
+            let position = -(rotation * self.get_axis_vector(3));
+            let r = rotation.data;
+
+            Some(Self::new([
+                r[0], r[1], r[2], position.x,
+                r[3], r[4], r[5], position.y,
+                r[6], r[7], r[8], position.z,
+            ]))
+        }
+
+        /// Transforms the given point by this matrix.
+        pub fn transform(&self, vector: Vec3) -> Vec3 {
+            *self * vector
+        }
+
+        /// Transforms the given point by the inverse of this matrix.
+        /// This assumes that the rotation part is orthonormal, such as
+        /// the transform of a rigid body.
+        pub fn transform_inverse(&self, vector: Vec3) -> Vec3 {
+            let tmp = vector - self.get_axis_vector(3);
+            self.transform_inverse_direction(tmp)
+        }
+
+        /// Transforms the given direction by this matrix, ignoring the
+        /// translation.
+        pub fn transform_direction(&self, vector: Vec3) -> Vec3 {
+            Vec3::new(
+                vector.x * self.data[0] + vector.y * self.data[1] + vector.z * self.data[2],
+                vector.x * self.data[4] + vector.y * self.data[5] + vector.z * self.data[6],
+                vector.x * self.data[8] + vector.y * self.data[9] + vector.z * self.data[10]
+            )
+        }
+
+        /// Transforms the given direction by the inverse of this matrix,
+        /// ignoring the translation. This assumes that the rotation part
+        /// is orthonormal, such as the transform of a rigid body.
+        pub fn transform_inverse_direction(&self, vector: Vec3) -> Vec3 {
+            Vec3::new(
+                vector.x * self.data[0] + vector.y * self.data[4] + vector.z * self.data[8],
+                vector.x * self.data[1] + vector.y * self.data[5] + vector.z * self.data[9],
+                vector.x * self.data[2] + vector.y * self.data[6] + vector.z * self.data[10]
+            )
+        }
+    }
+
+    impl Mul<Vec3> for Matrix4 {
+        type Output = Vec3;
+
+        /// Transforms the given point by this matrix.
+        fn mul(self, vector: Vec3) -> Self::Output {
+            Vec3::new(
+                vector.x * self.data[0] + vector.y * self.data[1] + vector.z * self.data[2] + self.data[3],
+                vector.x * self.data[4] + vector.y * self.data[5] + vector.z * self.data[6] + self.data[7],
+                vector.x * self.data[8] + vector.y * self.data[9] + vector.z * self.data[10] + self.data[11]
+            )
+        }
+    }
+
+    impl Mul<Matrix4> for Matrix4 {
+        type Output = Self;
+
+        /// Composes the transforms, the result applies `other` first
+        /// and then this one.
+        fn mul(self, other: Matrix4) -> Self::Output {
+            let rotation = self.get_rotation() * other.get_rotation();
+            let position = self * other.get_axis_vector(3);
+            let r = rotation.data;
+
+            Self::new([
+                r[0], r[1], r[2], position.x,
+                r[3], r[4], r[5], position.y,
+                r[6], r[7], r[8], position.z,
+            ])
+        }
+    }
+
+    impl MulAssign<Matrix4> for Matrix4 {
+        fn mul_assign(&mut self, other: Matrix4) {
+            *self = *self * other;
+        }
+    }
+}
+
+pub mod particle {
+    use super::precision::*;
+    use super::core::*;
+
+    /// The numerical method used to integrate the motion of a particle.
+    ///
+    /// The acceleration (including the accumulated forces) is taken as
+    /// constant during the step, while the damping acts as a drag that
+    /// is proportional to the velocity.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+    pub enum Integrator {
+        /// Updates the velocity first and then the position with the
+        /// new velocity. Cheap and stable, first order accurate.
+        #[default]
+        SemiImplicitEuler,
+        /// Updates the position using both the velocity and the
+        /// acceleration, exact for constant accelerations.
+        VelocityVerlet,
+        /// Classic fourth order Runge-Kutta, which also integrates the
+        /// damping accurately as a continuous drag.
+        RungeKutta4,
+    }
+
+    /// The default motion below which objects are put to sleep. The
+    /// motion is a recency-weighted average of the squared speed of the
+    /// object, so this is roughly a speed of half a unit per second.
+    pub const DEFAULT_SLEEP_EPSILON: Real = 0.3;
+
+    /// Returns the recency-weighted average of the given motion values,
+    /// where the previous average loses half of its weight every second.
+    pub(crate) fn recency_weighted_motion(motion: Real, current_motion: Real, duration: Real) -> Real {
+        let bias = (0.5 as Real).powf(duration);
+        bias * motion + (1.0 - bias) * current_motion
+    }
+
+    /// A snapshot of the kinematic state of a particle, used to
+    /// interpolate between simulation steps when rendering.
+    #[derive(Debug, Copy, Clone, PartialEq, Default)]
+    pub struct ParticleState {
+        /// The position of the particle.
+        pub position: Vec3,
+        /// The velocity of the particle.
+        pub velocity: Vec3,
+    }
+
+    impl ParticleState {
+        /// Linearly interpolates between this state (at `alpha` zero)
+        /// and the given one (at `alpha` one).
+        pub fn interpolate<T: AsReal>(&self, other: &ParticleState, alpha: T) -> ParticleState {
+            let alpha = alpha.as_real();
+
+            ParticleState {
+                position: self.position * (1.0 - alpha) + other.position * alpha,
+                velocity: self.velocity * (1.0 - alpha) + other.velocity * alpha,
+            }
+        }
+    }
+
+    /// A particle is the simplest object that can be simulated in
+    /// the physics system.
+    #[allow(unused)]
+    #[derive(Debug)]
+    pub struct Particle {
+        /// Holds the linear position of the particle in world space
+        position: Vec3,
+        /// Holds the linear velocity of the particle in world space
+        velocity: Vec3,
+        /// Holds the acceleration of the particle.
+        acceleration: Vec3,
+        /// Holds the amount of damping applied to linear motion.
+        damping: Real,
+        /// Holds the inverse of the mass of the particle.
+        inverse_mass: Real,
+        /// Holds the accumulated force to be applied at the next
+        /// simulation iteration.
+        force_accum: Vec3,
+        /// Holds the acceleration the particle had in the last
+        /// integration step, used to detect resting contacts.
+        last_frame_acceleration: Vec3,
+        /// Holds the method used to integrate the particle.
+        integrator: Integrator,
+        /// A particle can be put to sleep to avoid it being updated
+        /// by the integration functions or affected by collisions
+        /// with the world.
+        is_awake: bool,
+        /// Some particles may never be allowed to fall asleep, for
+        /// instance the ones under the player's control.
+        can_sleep: bool,
+        /// Holds the amount of motion of the particle. This is a
+        /// recency weighted mean that can be used to put a particle
+        /// to sleep.
+        motion: Real,
+        /// Holds the motion below which the particle is put to sleep.
+        sleep_epsilon: Real,
+    }
+
+    pub trait ParticleLike{
+        fn integrate<T: AsReal>(&mut self, duration: T);
+        fn integrate_with<T: AsReal>(&mut self, integrator: Integrator, duration: T);
+        fn get_integrator(&self) -> Integrator;
+        fn set_integrator(&mut self, integrator: Integrator);
+        fn get_mass(&self) -> Real;
+        fn set_mass<T: AsReal>(&mut self, mass: T);
+        fn get_inverse_mass(&self) -> Real;
+        fn set_inverse_mass<T: AsReal>(&mut self, inverse_mass: T);
+        fn has_finite_mass(&self) -> bool;
+        fn get_position(&self) -> Vec3;
+        fn set_position(&mut self, position: Vec3);
+        fn get_velocity(&self) -> Vec3;
+        fn set_velocity(&mut self, velocity: Vec3);
+        fn get_acceleration(&self) -> Vec3;
+        fn set_acceleration(&mut self, acceleration: Vec3);
+        fn get_damping(&self) -> Real;
+        fn set_damping<T: AsReal>(&mut self, damping: T);
+        fn add_force(&mut self, force: Vec3);
+        fn clear_accumulator(&mut self);
+        fn get_force_accumulator(&self) -> Vec3;
+        fn get_last_frame_acceleration(&self) -> Vec3;
+        fn is_awake(&self) -> bool;
+        fn set_awake(&mut self, awake: bool);
+        fn get_can_sleep(&self) -> bool;
+        fn set_can_sleep(&mut self, can_sleep: bool);
+        fn get_motion(&self) -> Real;
+        fn get_sleep_epsilon(&self) -> Real;
+        fn set_sleep_epsilon<T: AsReal>(&mut self, sleep_epsilon: T);
+    }
+
+    /// Returns the inverse of the given mass, panicking if it is zero.
+    fn inverse_of_mass(mass: Real) -> Real {
+        if mass == 0 as Real {
+            panic!("Mass can not be zero!");
+        }
+
+        1.0 / mass
+    }
+
+    impl ParticleLike for Particle {
+        /// Integrates the particle forward in time by the given amount,
+        /// using the integrator of the particle. Particles with infinite
+        /// mass or asleep are never moved.
+        fn integrate<T: AsReal>(&mut self, duration: T) {
+            self.integrate_with(self.integrator, duration);
+        }
+
+        /// Integrates the particle forward in time by the given amount,
+        /// using the given integrator instead of the particle's one.
+        fn integrate_with<T: AsReal>(&mut self, integrator: Integrator, duration: T) {
+            let duration = duration.as_real();
+
+            // We don't integrate things with infinite mass, and sleeping
+            // particles ignore the forces applied to them.
+            if !self.has_finite_mass() || !self.is_awake {
+                self.clear_accumulator();
+                return;
+            }
+
+            if duration > 0 as Real {
+                // Work out the acceleration from the force
+                let mut acc = self.acceleration;
+                acc.add_scaled_vector(self.force_accum, self.inverse_mass);
+                self.last_frame_acceleration = acc;
+
+                match integrator {
+                    Integrator::SemiImplicitEuler => {
+                        // Update linear velocity from the acceleration
+                        // and impose drag
+                        self.velocity += acc * duration;
+                        self.velocity *= self.damping.powf(duration);
+
+                        // Update linear position with the new velocity
+                        self.position += self.velocity * duration;
+                    },
+                    Integrator::VelocityVerlet => {
+                        // Update linear position
+                        self.position += self.velocity * duration;
+                        self.position.add_scaled_vector(acc, 0.5 * duration * duration);
+
+                        // Update linear velocity and impose drag
+                        self.velocity += acc * duration;
+                        self.velocity *= self.damping.powf(duration);
+                    },
+                    Integrator::RungeKutta4 => {
+                        self.integrate_rk4(acc, duration);
+                    },
+                }
+
+                // Clear the forces.
+                self.clear_accumulator();
+
+                // Update the kinetic energy store, and possibly put the
+                // particle to sleep.
+                if self.can_sleep {
+                    self.update_motion(duration);
+                }
+            }
+        }
+
+        fn get_integrator(&self) -> Integrator {
+            self.integrator
+        }
+
+        fn set_integrator(&mut self, integrator: Integrator) {
+            self.integrator = integrator;
+        }
+
+        fn get_position(&self) -> Vec3 {
+            self.position.clone()
+        }
+
+        fn get_velocity(&self) -> Vec3 {
+            self.velocity.clone()
+        }
+
+        fn get_acceleration(&self) -> Vec3 {
+            self.acceleration.clone()
+        }
+
+        /// Returns the mass of the particle, or the maximum real
+        /// value if the particle has infinite mass.
+        fn get_mass(&self) -> Real {
+            if self.inverse_mass == 0 as Real {
+                REAL_MAX
+            } else {
+                1.0 / self.inverse_mass
+            }
+        }
+
+        /// Sets the mass of the particle, which can not be zero. Use
+        /// `set_inverse_mass(0)` to make the particle immovable.
+        fn set_mass<T: AsReal>(&mut self, mass: T) {
+            self.inverse_mass = inverse_of_mass(mass.as_real());
+        }
+
+        fn get_inverse_mass(&self) -> Real {
+            self.inverse_mass
+        }
+
+        /// Sets the inverse mass of the particle. A value of zero
+        /// makes the particle immovable (infinite mass).
+        fn set_inverse_mass<T: AsReal>(&mut self, inverse_mass: T) {
+            self.inverse_mass = inverse_mass.as_real();
+        }
+
+        /// Returns true if the mass of the particle is not infinite.
+        fn has_finite_mass(&self) -> bool {
+            self.inverse_mass > 0 as Real
+        }
+
+        fn set_position(&mut self, position: Vec3) {
+            self.position = position;
+        }
+
+        fn set_velocity(&mut self, velocity: Vec3) {
+            self.velocity = velocity;
+        }
+
+        fn set_acceleration(&mut self, acceleration: Vec3) {
+            self.acceleration = acceleration;
+        }
+
+        fn set_damping<T: AsReal>(&mut self, damping: T) {
+            self.damping = damping.as_real();
+        }
+
+        fn get_damping(&self) -> Real {
+            self.damping
+        }
+
+        /// Adds the given force to the particle, to be applied at the
+        /// next iteration only. This wakes the particle up.
+        fn add_force(&mut self, force: Vec3) {
+            self.force_accum += force;
+
+            if !self.is_awake {
+                self.set_awake(true);
+            }
+        }
+
+        /// Clears the forces applied to the particle. This will be
+        /// called automatically after each integration step.
+        fn clear_accumulator(&mut self) {
+            self.force_accum = Vec3::default();
+        }
+
+        /// Returns the force accumulated for the next iteration.
+        fn get_force_accumulator(&self) -> Vec3 {
+            self.force_accum
+        }
+
+        /// Returns the acceleration used in the last integration step.
+        fn get_last_frame_acceleration(&self) -> Vec3 {
+            self.last_frame_acceleration
+        }
+
+        /// Returns true if the particle is awake and responding to
+        /// integration.
+        fn is_awake(&self) -> bool {
+            self.is_awake
+        }
+
+        /// Sets the awake state of the particle. If the particle is put
+        /// to sleep, it will not integrate its motion, and its velocity
+        /// is removed. Adding forces does not wake a particle up.
+        fn set_awake(&mut self, awake: bool) {
+            if awake {
+                self.is_awake = true;
+
+                // Add a bit of motion to avoid it falling asleep immediately.
+                self.motion = self.sleep_epsilon * 2.0;
+            } else {
+                self.is_awake = false;
+                self.velocity = Vec3::default();
+            }
+        }
+
+        /// Returns true if the particle is allowed to fall asleep.
+        fn get_can_sleep(&self) -> bool {
+            self.can_sleep
+        }
+
+        /// Sets whether the particle is ever allowed to go to sleep.
+        /// A sleeping particle that can no longer sleep is woken up.
+        fn set_can_sleep(&mut self, can_sleep: bool) {
+            self.can_sleep = can_sleep;
+
+            if !can_sleep && !self.is_awake {
+                self.set_awake(true);
+            }
+        }
+
+        /// Returns the recency-weighted average of the squared speed
+        /// of the particle.
+        fn get_motion(&self) -> Real {
+            self.motion
+        }
+
+        fn get_sleep_epsilon(&self) -> Real {
+            self.sleep_epsilon
+        }
+
+        /// Sets the motion below which the particle is put to sleep.
+        fn set_sleep_epsilon<T: AsReal>(&mut self, sleep_epsilon: T) {
+            self.sleep_epsilon = sleep_epsilon.as_real();
+        }
+    }
+
+    impl Particle {
+        /// Creates a new particle at the give position
+        pub fn from_position<T, U>(position: Vec3, mass: T, velocity: Vec3, acceleration: Vec3, damping: U) -> Self
+        where T: AsReal, U: AsReal {
+            let damping = damping.as_real();
+            let inverse_mass = inverse_of_mass(mass.as_real());
+            let force_accum = Vec3::default();
+            let last_frame_acceleration = Vec3::default();
+            let integrator = Integrator::default();
+            let sleep_epsilon = DEFAULT_SLEEP_EPSILON;
+            Particle {
+                position, velocity, acceleration, damping,
+                inverse_mass, force_accum, last_frame_acceleration, integrator,
+                is_awake: true, can_sleep: true, motion: sleep_epsilon * 2.0, sleep_epsilon
+            }
+        }
+
+        /// Returns a snapshot of the position and velocity of the particle.
+        pub fn get_state(&self) -> ParticleState {
+            ParticleState { position: self.position, velocity: self.velocity }
+        }
+
+        /// Updates the recency-weighted motion of the particle after
+        /// an integration step, putting it to sleep once it is low enough.
+        fn update_motion(&mut self, duration: Real) {
+            let current_motion = self.velocity.square_magnitude();
+            self.motion = recency_weighted_motion(self.motion, current_motion, duration);
+
+            if self.motion < self.sleep_epsilon {
+                self.set_awake(false);
+            } else if self.motion > 10.0 * self.sleep_epsilon {
+                self.motion = 10.0 * self.sleep_epsilon;
+            }
+        }
+
+        /// Integrates the motion with the classic Runge-Kutta method,
+        /// where the damping is a drag with `dv/dt = acc + ln(damping) * v`.
+        fn integrate_rk4(&mut self, acc: Vec3, duration: Real) {
+            // A null damping stops the particle, there is no drag to integrate
+            if self.damping <= 0 as Real {
+                self.velocity = Vec3::default();
+                return;
+            }
+
+            let drag = self.damping.ln();
+            let derivative = |velocity: Vec3| acc + velocity * drag;
+
+            let v1 = self.velocity;
+            let a1 = derivative(v1);
+            let v2 = v1 + a1 * (0.5 * duration);
+            let a2 = derivative(v2);
+            let v3 = v1 + a2 * (0.5 * duration);
+            let a3 = derivative(v3);
+            let v4 = v1 + a3 * duration;
+            let a4 = derivative(v4);
+
+            let sixth = duration / 6.0;
+            self.position += (v1 + (v2 + v3) * 2.0 + v4) * sixth;
+            self.velocity += (a1 + (a2 + a3) * 2.0 + a4) * sixth;
+        }
+
+        /// Creates an immovable particle (with infinite mass) at the
+        /// given position, useful for anchors and walls.
+        pub fn new_static(position: Vec3) -> Self {
+            let mut particle = Particle::from_position(position, 1, Vec3::default(), Vec3::default(), 1);
+            particle.set_inverse_mass(0);
+            particle
+        }
+
+        /// Creates a new particles and set the position automatically to the origin.
+        pub fn new<T, U>(mass: T, velocity: Vec3, acceleration: Vec3, damping: U) -> Self
+        where T: AsReal, U: AsReal {
+            let position = Vec3::default();
+            Particle::from_position(position, mass, velocity, acceleration, damping)
+        }
+    }
+}
+
+pub mod particle_forces {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::precision::*;
+    use super::core::*;
+    use super::particle::*;
+
+    /// A force generator can be asked to add a force to one or more
+    /// particles.
+    pub trait ParticleForceGenerator {
+        /// Calculates and updates the force applied to the particle
+        /// at the given index of the particles slice.
+        fn update_force(&mut self, particles: &mut [Particle], index: usize, duration: Real);
+    }
+
+    /// A force generator that applies a gravitational force. One
+    /// instance can be used for multiple particles.
+    #[derive(Debug, Clone)]
+    pub struct ParticleGravity {
+        /// Holds the acceleration due to gravity.
+        gravity: Vec3,
+    }
+
+    impl ParticleGravity {
+        /// Creates the generator with the given acceleration.
+        pub fn new(gravity: Vec3) -> Self {
+            ParticleGravity { gravity }
+        }
+    }
+
+    impl ParticleForceGenerator for ParticleGravity {
+        /// Applies the gravitational force to the given particle.
+        fn update_force(&mut self, particles: &mut [Particle], index: usize, _duration: Real) {
+            let particle = &mut particles[index];
+
+            // Check that we do not have infinite mass
+            if !particle.has_finite_mass() {
+                return;
+            }
+
+            // Apply the mass-scaled force to the particle
+            let force = self.gravity * particle.get_mass();
+            particle.add_force(force);
+        }
+    }
+
+    /// A force generator that applies a drag force, with both a
+    /// linear and a squared coefficient of the particle speed.
+    #[derive(Debug, Clone)]
+    pub struct ParticleDrag {
+        /// Holds the velocity drag coefficient.
+        k1: Real,
+        /// Holds the velocity squared drag coefficient.
+        k2: Real,
+    }
+
+    impl ParticleDrag {
+        /// Creates the generator with the given coefficients.
+        pub fn new<T, U>(k1: T, k2: U) -> Self
+        where T: AsReal, U: AsReal {
+            ParticleDrag { k1: k1.as_real(), k2: k2.as_real() }
+        }
+    }
+
+    impl ParticleForceGenerator for ParticleDrag {
+        /// Applies the drag force to the given particle.
+        fn update_force(&mut self, particles: &mut [Particle], index: usize, _duration: Real) {
+            let particle = &mut particles[index];
+            let mut force = particle.get_velocity();
+
+            // Calculate the total drag coefficient
+            let speed = force.magnitude();
+            let drag_coeff = self.k1 * speed + self.k2 * speed * speed;
+
+            // Calculate the final force and apply it
+            force.normalize();
+            force *= -drag_coeff;
+            particle.add_force(force);
+        }
+    }
+
+    /// A force generator that applies a spring force between the
+    /// particle it is registered to and another particle.
+    #[derive(Debug, Clone)]
+    pub struct ParticleSpring {
+        /// The index of the particle at the other end of the spring.
+        other: usize,
+        /// Holds the spring constant.
+        spring_constant: Real,
+        /// Holds the rest length of the spring.
+        rest_length: Real,
+    }
+
+    impl ParticleSpring {
+        /// Creates a new spring to the particle at the given index.
+        pub fn new<T, U>(other: usize, spring_constant: T, rest_length: U) -> Self
+        where T: AsReal, U: AsReal {
+            ParticleSpring {
+                other,
+                spring_constant: spring_constant.as_real(),
+                rest_length: rest_length.as_real(),
+            }
+        }
+    }
+
+    impl ParticleForceGenerator for ParticleSpring {
+        /// Applies the spring force to the given particle.
+        fn update_force(&mut self, particles: &mut [Particle], index: usize, _duration: Real) {
+            // Calculate the vector of the spring
+            let mut force = particles[index].get_position();
+            force -= particles[self.other].get_position();
+
+            // Calculate the magnitude of the force
+            let magnitude = (force.magnitude() - self.rest_length) * self.spring_constant;
+
+            // Calculate the final force and apply it
+            force.normalize();
+            force *= -magnitude;
+            particles[index].add_force(force);
+        }
+    }
+
+    /// A force generator that applies a spring force, where one end
+    /// is attached to a fixed point in space.
+    #[derive(Debug, Clone)]
+    pub struct ParticleAnchoredSpring {
+        /// The location of the anchored end of the spring.
+        anchor: Vec3,
+        /// Holds the spring constant.
+        spring_constant: Real,
+        /// Holds the rest length of the spring.
+        rest_length: Real,
+    }
+
+    impl ParticleAnchoredSpring {
+        /// Creates a new spring attached to the given anchor point.
+        pub fn new<T, U>(anchor: Vec3, spring_constant: T, rest_length: U) -> Self
+        where T: AsReal, U: AsReal {
+            ParticleAnchoredSpring {
+                anchor,
+                spring_constant: spring_constant.as_real(),
+                rest_length: rest_length.as_real(),
+            }
+        }
+
+        /// Returns the location of the anchored end of the spring.
+        pub fn get_anchor(&self) -> Vec3 {
+            self.anchor
+        }
+
+        /// Sets the location of the anchored end of the spring.
+        pub fn set_anchor(&mut self, anchor: Vec3) {
+            self.anchor = anchor;
+        }
+    }
+
+    impl ParticleForceGenerator for ParticleAnchoredSpring {
+        /// Applies the spring force to the given particle.
+        fn update_force(&mut self, particles: &mut [Particle], index: usize, _duration: Real) {
+            // Calculate the vector of the spring
+            let mut force = particles[index].get_position();
+            force -= self.anchor;
+
+            // Calculate the magnitude of the force
+            let magnitude = (self.rest_length - force.magnitude()) * self.spring_constant;
+
+            // Calculate the final force and apply it
+            force.normalize();
+            force *= magnitude;
+            particles[index].add_force(force);
+        }
+    }
+
+    /// Keeps track of one force generator and the particle it
+    /// applies to.
+    pub struct ParticleForceRegistration {
+        /// The index of the particle the force is applied to.
+        pub particle: usize,
+        /// The generator of the force.
+        pub generator: Rc<RefCell<dyn ParticleForceGenerator>>,
+    }
+
+    /// Holds all the force generators and the particles they apply to.
+    #[derive(Default)]
+    pub struct ParticleForceRegistry {
+        /// Holds the list of registrations.
+        registrations: Vec<ParticleForceRegistration>,
+    }
+
+    impl ParticleForceRegistry {
+        /// Creates a new empty registry.
+        pub fn new() -> Self {
+            ParticleForceRegistry { registrations: Vec::new() }
+        }
+
+        /// Registers the given force generator to apply to the
+        /// particle at the given index.
+        pub fn add(&mut self, particle: usize, generator: Rc<RefCell<dyn ParticleForceGenerator>>) {
+            self.registrations.push(ParticleForceRegistration { particle, generator });
+        }
+
+        /// Removes the given registered pair from the registry. If the
+        /// pair is not registered, this method will have no effect.
+        pub fn remove(&mut self, particle: usize, generator: &Rc<RefCell<dyn ParticleForceGenerator>>) {
+            self.registrations.retain(|registration| {
+                registration.particle != particle || !Rc::ptr_eq(&registration.generator, generator)
+            });
+        }
+
+        /// Clears all registrations from the registry. This will not
+        /// delete the particles or the force generators themselves,
+        /// just the records of their connection.
+        pub fn clear(&mut self) {
+            self.registrations.clear();
+        }
+
+        /// Returns the number of registrations in the registry.
+        pub fn len(&self) -> usize {
+            self.registrations.len()
+        }
+
+        /// Returns true if there are no registrations in the registry.
+        pub fn is_empty(&self) -> bool {
+            self.registrations.is_empty()
+        }
+
+        /// Calls all the force generators to update the forces of
+        /// their corresponding particles.
+        pub fn update_forces<T: AsReal>(&mut self, particles: &mut [Particle], duration: T) {
+            let duration = duration.as_real();
+
+            for registration in self.registrations.iter() {
+                registration.generator
+                    .borrow_mut()
+                    .update_force(particles, registration.particle, duration);
+            }
+        }
+    }
+}
+
+pub mod particle_contacts {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::precision::*;
+    use super::core::*;
+    use super::particle::*;
+    use super::collide_coarse::*;
+
+    /// A contact represents two objects in contact (in this case
+    /// a particle contact represents two particles). Resolving a
+    /// contact removes their interpenetration, and applies sufficient
+    /// impulse to keep them apart. Colliding bodies may also rebound.
+    ///
+    /// To resolve a set of contacts, use the `ParticleContactResolver`.
+    #[derive(Debug, Clone)]
+    pub struct ParticleContact {
+        /// Holds the indices of the particles that are involved in the
+        /// contact. The second of these can be `None` for contacts
+        /// with the scenery.
+        pub particles: [Option<usize>; 2],
+        /// Holds the normal restitution coefficient at the contact.
+        pub restitution: Real,
+        /// Holds the direction of the contact in world coordinates.
+        pub contact_normal: Vec3,
+        /// Holds the depth of penetration at the contact.
+        pub penetration: Real,
+        /// Holds the friction coefficient at the contact, limiting the
+        /// tangential impulse to a fraction of the normal one.
+        pub friction: Real,
+        /// Holds the amount each particle is moved by during
+        /// interpenetration resolution.
+        particle_movement: [Vec3; 2],
+    }
+
+    impl ParticleContact {
+        /// Creates a new contact between the given particle and another
+        /// particle, or the scenery if `other` is `None`.
+        pub fn new<T, U>(particle: usize, other: Option<usize>, contact_normal: Vec3, penetration: T, restitution: U) -> Self
+        where T: AsReal, U: AsReal {
+            ParticleContact {
+                particles: [Some(particle), other],
+                restitution: restitution.as_real(),
+                contact_normal,
+                penetration: penetration.as_real(),
+                friction: 0 as Real,
+                particle_movement: [Vec3::default(); 2],
+            }
+        }
+
+        /// Returns the amount each particle was moved by during the
+        /// last interpenetration resolution.
+        pub fn get_particle_movement(&self) -> [Vec3; 2] {
+            self.particle_movement
+        }
+
+        /// Resolves this contact, for both velocity and interpenetration.
+        pub fn resolve<T: AsReal>(&mut self, particles: &mut [Particle], duration: T) {
+            let duration = duration.as_real();
+            self.match_awake_state(particles);
+            self.resolve_velocity(particles, duration, 0 as Real);
+            self.resolve_interpenetration(particles, duration);
+        }
+
+        /// Updates the awake state of the particles that are taking
+        /// part in the contact. A particle will be made awake if it is
+        /// in contact with a particle that is awake. Collisions with the
+        /// world never cause a particle to wake up.
+        fn match_awake_state(&self, particles: &mut [Particle]) {
+            let (first, second) = match self.particles {
+                [Some(first), Some(second)] => (first, second),
+                _ => return,
+            };
+
+            // Wake up only the sleeping one
+            if particles[first].is_awake() != particles[second].is_awake() {
+                if particles[first].is_awake() {
+                    particles[second].set_awake(true);
+                } else {
+                    particles[first].set_awake(true);
+                }
+            }
+        }
+
+        /// Calculates the separating velocity at this contact.
+        pub fn calculate_separating_velocity(&self, particles: &[Particle]) -> Real {
+            let mut relative_velocity = self.first(particles).get_velocity();
+
+            if let Some(other) = self.particles[1] {
+                relative_velocity -= particles[other].get_velocity();
+            }
+
+            relative_velocity.dot(self.contact_normal)
+        }
+
+        /// Returns the first particle of the contact, which must be set.
+        fn first<'a>(&self, particles: &'a [Particle]) -> &'a Particle {
+            match self.particles[0] {
+                Some(index) => &particles[index],
+                None => panic!("The first particle of a contact must be set!"),
+            }
+        }
+
+        /// Returns the sum of the inverse masses of the particles.
+        fn total_inverse_mass(&self, particles: &[Particle]) -> Real {
+            let mut total_inverse_mass = self.first(particles).get_inverse_mass();
+
+            if let Some(other) = self.particles[1] {
+                total_inverse_mass += particles[other].get_inverse_mass();
+            }
+
+            total_inverse_mass
+        }
+
+        /// Handles the impulse calculations for this collision. Closing
+        /// velocities slower than the resting threshold don't bounce.
+        fn resolve_velocity(&mut self, particles: &mut [Particle], duration: Real, resting_threshold: Real) {
+            // Find the velocity in the direction of the contact
+            let separating_velocity = self.calculate_separating_velocity(particles);
+
+            // Check if it needs to be resolved
+            if separating_velocity > 0 as Real {
+                // The contact is either separating, or stationary -
+                // there's no impulse required.
+                return;
+            }
+
+            // Slow contacts are treated as resting, with no bounce
+            let restitution = if -separating_velocity < resting_threshold {
+                0 as Real
+            } else {
+                self.restitution
+            };
+
+            // Calculate the new separating velocity
+            let mut new_sep_velocity = -separating_velocity * restitution;
+
+            // Check the velocity build-up due to acceleration only
+            let mut acc_caused_velocity = self.first(particles).get_last_frame_acceleration();
+
+            if let Some(other) = self.particles[1] {
+                acc_caused_velocity -= particles[other].get_last_frame_acceleration();
+            }
+
+            let acc_caused_sep_velocity = acc_caused_velocity.dot(self.contact_normal) * duration;
+
+            // If we've got a closing velocity due to acceleration build-up,
+            // remove it from the new separating velocity
+            if acc_caused_sep_velocity < 0 as Real {
+                new_sep_velocity += restitution * acc_caused_sep_velocity;
+
+                // Make sure we haven't removed more than was there to remove.
+                if new_sep_velocity < 0 as Real {
+                    new_sep_velocity = 0 as Real;
+                }
+            }
+
+            let delta_velocity = new_sep_velocity - separating_velocity;
+
+            // We apply the change in velocity to each object in proportion
+            // to their inverse mass (i.e. those with lower inverse mass
+            // [higher actual mass] get less change in velocity).
+            let total_inverse_mass = self.total_inverse_mass(particles);
+
+            // If all particles have infinite mass, then impulses have no effect
+            if total_inverse_mass <= 0 as Real {
+                return;
+            }
+
+            // Calculate the impulse to apply
+            let impulse = delta_velocity / total_inverse_mass;
+
+            // Find the amount of impulse per unit of inverse mass
+            let impulse_per_imass = self.contact_normal * impulse;
+
+            // Apply impulses: they are applied in the direction of the
+            // contact, and are proportional to the inverse mass.
+            self.apply_impulse(particles, impulse_per_imass);
+
+            if self.friction > 0 as Real {
+                self.apply_friction(particles, impulse, total_inverse_mass);
+            }
+        }
+
+        /// Applies the given impulse per unit of inverse mass to the
+        /// first particle, and its opposite to the second one.
+        fn apply_impulse(&self, particles: &mut [Particle], impulse_per_imass: Vec3) {
+            for (i, index) in self.particles.iter().enumerate() {
+                if let Some(index) = *index {
+                    let particle = &mut particles[index];
+                    let sign = if i == 0 { 1 as Real } else { -1 as Real };
+                    let mut velocity = particle.get_velocity();
+                    velocity.add_scaled_vector(impulse_per_imass, sign * particle.get_inverse_mass());
+                    particle.set_velocity(velocity);
+                }
+            }
+        }
+
+        /// Removes tangential velocity at the contact, limited by the
+        /// friction coefficient times the normal impulse applied.
+        fn apply_friction(&self, particles: &mut [Particle], normal_impulse: Real, total_inverse_mass: Real) {
+            // Find the relative velocity across the contact plane
+            let mut relative_velocity = self.first(particles).get_velocity();
+
+            if let Some(other) = self.particles[1] {
+                relative_velocity -= particles[other].get_velocity();
+            }
+
+            let mut tangent_velocity = relative_velocity;
+            tangent_velocity.add_scaled_vector(self.contact_normal, -relative_velocity.dot(self.contact_normal));
+
+            let tangent_speed = tangent_velocity.magnitude();
+
+            if tangent_speed <= 0 as Real {
+                return;
+            }
+
+            // Stop the sliding, but no more than friction allows
+            let max_friction = self.friction * normal_impulse.abs();
+            let friction_impulse = (tangent_speed / total_inverse_mass).min(max_friction);
+
+            tangent_velocity.normalize();
+            self.apply_impulse(particles, tangent_velocity * -friction_impulse);
+        }
+
+        /// Handles the interpenetration resolution for this contact.
+        fn resolve_interpenetration(&mut self, particles: &mut [Particle], _duration: Real) {
+            // If we don't have any penetration, skip this step.
+            if self.penetration <= 0 as Real {
+                self.particle_movement = [Vec3::default(); 2];
+                return;
+            }
+
+            // The movement of each object is based on their inverse mass,
+            // so total that.
+            let total_inverse_mass = self.total_inverse_mass(particles);
+
+            // If all particles have infinite mass, then we do nothing
+            if total_inverse_mass <= 0 as Real {
+                self.particle_movement = [Vec3::default(); 2];
+                return;
+            }
+
+            // Find the amount of penetration resolution per unit of
+            // inverse mass
+            let move_per_imass = self.contact_normal * (self.penetration / total_inverse_mass);
+
+            // Calculate and apply the movement amounts
+            for (i, index) in self.particles.iter().enumerate() {
+                self.particle_movement[i] = match *index {
+                    Some(index) => {
+                        let particle = &mut particles[index];
+                        let sign = if i == 0 { 1 as Real } else { -1 as Real };
+                        let movement = move_per_imass * (sign * particle.get_inverse_mass());
+                        particle.set_position(particle.get_position() + movement);
+                        movement
+                    },
+                    None => Vec3::default(),
+                };
+            }
+        }
+    }
+
+    /// The default closing velocity below which contacts are resting.
+    pub const DEFAULT_RESTING_THRESHOLD: Real = 0.25;
+
+    /// The contact resolution routine for particle contacts. One
+    /// resolver instance can be shared for the whole simulation.
+    #[derive(Debug, Clone)]
+    pub struct ParticleContactResolver {
+        /// Holds the number of iterations allowed.
+        iterations: usize,
+        /// Holds the closing velocity below which contacts are
+        /// considered resting, and so resolved without bouncing.
+        resting_threshold: Real,
+        /// This is a performance tracking value; we keep a record
+        /// of the actual number of iterations used.
+        iterations_used: usize,
+    }
+
+    impl ParticleContactResolver {
+        /// Creates a new contact resolver with the given number of
+        /// iterations allowed.
+        pub fn new(iterations: usize) -> Self {
+            ParticleContactResolver {
+                iterations,
+                resting_threshold: DEFAULT_RESTING_THRESHOLD,
+                iterations_used: 0,
+            }
+        }
+
+        /// Sets the number of iterations that can be used.
+        pub fn set_iterations(&mut self, iterations: usize) {
+            self.iterations = iterations;
+        }
+
+        /// Sets the closing velocity below which contacts are
+        /// considered resting, and so resolved without bouncing.
+        pub fn set_resting_threshold<T: AsReal>(&mut self, resting_threshold: T) {
+            self.resting_threshold = resting_threshold.as_real();
+        }
+
+        /// Returns the closing velocity below which contacts are
+        /// considered resting.
+        pub fn get_resting_threshold(&self) -> Real {
+            self.resting_threshold
+        }
+
+        /// Returns the number of iterations used in the last call
+        /// to resolve contacts.
+        pub fn get_iterations_used(&self) -> usize {
+            self.iterations_used
+        }
+
+        /// Resolves a set of particle contacts for both penetration
+        /// and velocity, always resolving the most severe contact first
+        /// and updating the penetration of the others accordingly.
+        ///
+        /// ### Arguments
+        /// * `contacts` - The contacts to be resolved.
+        /// * `particles` - The particles referenced by the contacts.
+        /// * `duration` - The duration of the previous integration step.
+        pub fn resolve_contacts<T: AsReal>(&mut self, contacts: &mut [ParticleContact], particles: &mut [Particle], duration: T) {
+            let duration = duration.as_real();
+            self.iterations_used = 0;
+
+            while self.iterations_used < self.iterations {
+                // Find the contact with the largest closing velocity
+                let mut max = REAL_MAX;
+                let mut max_index = contacts.len();
+
+                for (i, contact) in contacts.iter().enumerate() {
+                    let sep_velocity = contact.calculate_separating_velocity(particles);
+
+                    if sep_velocity < max && (sep_velocity < 0 as Real || contact.penetration > 0 as Real) {
+                        max = sep_velocity;
+                        max_index = i;
+                    }
+                }
+
+                // Do we have anything worth resolving?
+                if max_index == contacts.len() {
+                    break;
+                }
+
+                // Resolve this contact
+                let contact = &mut contacts[max_index];
+                contact.match_awake_state(particles);
+                contact.resolve_velocity(particles, duration, self.resting_threshold);
+                contact.resolve_interpenetration(particles, duration);
+
+                // Update the interpenetrations for all particles
+                let resolved = contacts[max_index].particles;
+                let movement = contacts[max_index].particle_movement;
+
+                for contact in contacts.iter_mut() {
+                    for (i, index) in resolved.iter().enumerate() {
+                        if index.is_none() {
+                            continue;
+                        }
+
+                        if contact.particles[0] == *index {
+                            contact.penetration -= movement[i].dot(contact.contact_normal);
+                        } else if contact.particles[1] == *index {
+                            contact.penetration += movement[i].dot(contact.contact_normal);
+                        }
+                    }
+                }
+
+                self.iterations_used += 1;
+            }
+        }
+    }
+
+    /// This is the basic polymorphic interface for contact generators
+    /// applying to particles.
+    pub trait ParticleContactGenerator {
+        /// Fills the given contact list with the generated contacts,
+        /// without exceeding the given limit, and returns the number
+        /// of contacts that have been written.
+        fn add_contact(&mut self, particles: &[Particle], contacts: &mut Vec<ParticleContact>, limit: usize) -> usize;
+    }
+
+    /// Generates contacts for every particle crossing a plane, keeping
+    /// them on the side of the plane the normal points to. This can be
+    /// used for the ground or any other flat wall of the scenery.
+    #[derive(Debug, Clone)]
+    pub struct ParticleHalfSpaceContacts {
+        /// Holds the normal of the plane, pointing out of the half-space.
+        pub normal: Vec3,
+        /// Holds the distance of the plane from the origin.
+        pub offset: Real,
+        /// Holds the radius of the particles, which is zero for
+        /// point particles.
+        pub radius: Real,
+        /// Holds the restitution of the generated contacts.
+        pub restitution: Real,
+        /// Holds the friction of the generated contacts.
+        pub friction: Real,
+    }
+
+    impl ParticleHalfSpaceContacts {
+        /// Creates a new half-space with the plane given by its normal
+        /// and offset, where particles are treated as points.
+        pub fn new<T, U, V>(normal: Vec3, offset: T, restitution: U, friction: V) -> Self
+        where T: AsReal, U: AsReal, V: AsReal {
+            let mut normal = normal;
+            normal.normalize();
+
+            ParticleHalfSpaceContacts {
+                normal,
+                offset: offset.as_real(),
+                radius: 0 as Real,
+                restitution: restitution.as_real(),
+                friction: friction.as_real(),
+            }
+        }
+
+        /// Creates a ground plane at the given height, with the normal
+        /// pointing upwards along the y axis.
+        pub fn ground<T, U, V>(height: T, restitution: U, friction: V) -> Self
+        where T: AsReal, U: AsReal, V: AsReal {
+            ParticleHalfSpaceContacts::new(Vec3::new(0, 1, 0), height, restitution, friction)
+        }
+
+        /// Sets the radius of the particles colliding with the plane.
+        pub fn set_radius<T: AsReal>(&mut self, radius: T) {
+            self.radius = radius.as_real();
+        }
+    }
+
+    impl ParticleContactGenerator for ParticleHalfSpaceContacts {
+        /// Fills the given contact list with a contact for each particle
+        /// that has crossed the plane.
+        fn add_contact(&mut self, particles: &[Particle], contacts: &mut Vec<ParticleContact>, limit: usize) -> usize {
+            let mut used = 0;
+
+            for (index, particle) in particles.iter().enumerate() {
+                if used >= limit {
+                    break;
+                }
+
+                // Work out the distance of the particle from the plane
+                let distance = particle.get_position().dot(self.normal) - self.radius - self.offset;
+
+                // Touching the plane counts as being in contact with it
+                if distance <= 0 as Real {
+                    let mut contact = ParticleContact::new(
+                        index, None, self.normal, -distance, self.restitution
+                    );
+                    contact.friction = self.friction;
+                    contacts.push(contact);
+                    used += 1;
+                }
+            }
+
+            used
+        }
+    }
+
+    /// Generates contacts between the particles that collide with each
+    /// other, treating them as spheres of the same radius. The pairs of
+    /// particles to test are given by a broad phase, which can be picked
+    /// for the kind of scene simulated, and shared with the queries of
+    /// a particle world.
+    pub struct ParticleCollider {
+        /// Holds the broad phase finding the particles that may collide.
+        broad_phase: Rc<RefCell<dyn BroadPhase>>,
+        /// Holds the radius of the particles.
+        pub radius: Real,
+        /// Holds the restitution of the generated contacts.
+        pub restitution: Real,
+        /// Holds the friction of the generated contacts.
+        pub friction: Real,
+        /// Holds the bounding boxes of the particles, kept between
+        /// frames to avoid reallocating them.
+        volumes: Vec<Aabb>,
+    }
+
+    impl ParticleCollider {
+        /// Creates a new collider for particles of the given radius,
+        /// using the given broad phase.
+        pub fn new<T, U, V>(broad_phase: Rc<RefCell<dyn BroadPhase>>, radius: T, restitution: U, friction: V) -> Self
+        where T: AsReal, U: AsReal, V: AsReal {
+            ParticleCollider {
+                broad_phase,
+                radius: radius.as_real(),
+                restitution: restitution.as_real(),
+                friction: friction.as_real(),
+                volumes: Vec::new(),
+            }
+        }
+
+        /// Returns the broad phase of the collider.
+        pub fn get_broad_phase(&self) -> &Rc<RefCell<dyn BroadPhase>> {
+            &self.broad_phase
+        }
+    }
+
+    impl ParticleContactGenerator for ParticleCollider {
+        /// Fills the given contact list with a contact for each pair of
+        /// particles that overlap, unless they are both asleep.
+        fn add_contact(&mut self, particles: &[Particle], contacts: &mut Vec<ParticleContact>, limit: usize) -> usize {
+            let half_size = Vec3::new(self.radius, self.radius, self.radius);
+
+            self.volumes.clear();
+            self.volumes.extend(particles.iter().map(|particle| {
+                Aabb::from_centre_and_half_size(particle.get_position(), half_size)
+            }));
+            let mut broad_phase = self.broad_phase.borrow_mut();
+            broad_phase.update(&self.volumes);
+
+            let mut used = 0;
+
+            for potential in broad_phase.get_potential_contacts(usize::MAX) {
+                if used >= limit {
+                    break;
+                }
+
+                let [first, second] = potential.bodies;
+
+                if !particles[first].is_awake() && !particles[second].is_awake() {
+                    continue;
+                }
+
+                // The normal points from the second particle to the first
+                let midline = particles[first].get_position() - particles[second].get_position();
+                let distance = midline.magnitude();
+
+                if distance <= 0 as Real || distance >= 2.0 * self.radius {
+                    continue;
+                }
+
+                let mut contact = ParticleContact::new(
+                    first, Some(second), midline * (1.0 / distance), 2.0 * self.radius - distance, self.restitution
+                );
+                contact.friction = self.friction;
+                contacts.push(contact);
+                used += 1;
+            }
+
+            used
+        }
+    }
+}
+
+pub mod particle_links {
+    use super::precision::*;
+    use super::core::*;
+    use super::particle::*;
+    use super::particle_contacts::*;
+
+    /// Returns the unit vector pointing from `from` to `to`, and the
+    /// distance between the two points.
+    fn direction_and_length(from: Vec3, to: Vec3) -> (Vec3, Real) {
+        let mut normal = to - from;
+        let length = normal.magnitude();
+        normal.normalize();
+        (normal, length)
+    }
+
+    /// Cables link a pair of particles, generating a contact if they
+    /// stray too far apart.
+    #[derive(Debug, Clone)]
+    pub struct ParticleCable {
+        /// Holds the pair of particles that are connected by this link.
+        pub particles: [usize; 2],
+        /// Holds the maximum length of the cable.
+        pub max_length: Real,
+        /// Holds the restitution (bounciness) of the cable.
+        pub restitution: Real,
+    }
+
+    impl ParticleCable {
+        /// Creates a new cable between the two given particles.
+        pub fn new<T, U>(particles: [usize; 2], max_length: T, restitution: U) -> Self
+        where T: AsReal, U: AsReal {
+            ParticleCable {
+                particles,
+                max_length: max_length.as_real(),
+                restitution: restitution.as_real(),
+            }
+        }
+    }
+
+    impl ParticleContactGenerator for ParticleCable {
+        /// Fills the given contact list with the contact needed to
+        /// keep the cable from over-extending.
+        fn add_contact(&mut self, particles: &[Particle], contacts: &mut Vec<ParticleContact>, limit: usize) -> usize {
+            if limit == 0 {
+                return 0;
+            }
+
+            // Find the length of the cable
+            let (normal, length) = direction_and_length(
+                particles[self.particles[0]].get_position(),
+                particles[self.particles[1]].get_position()
+            );
+
+            // Check if we're over-extended
+            if length < self.max_length {
+                return 0;
+            }
+
+            // Otherwise return the contact
+            let penetration = length - self.max_length;
+            contacts.push(ParticleContact::new(
+                self.particles[0], Some(self.particles[1]), normal, penetration, self.restitution
+            ));
+
+            1
+        }
+    }
+
+    /// Rods link a pair of particles, generating a contact if they
+    /// stray too far apart or too close.
+    #[derive(Debug, Clone)]
+    pub struct ParticleRod {
+        /// Holds the pair of particles that are connected by this link.
+        pub particles: [usize; 2],
+        /// Holds the length of the rod.
+        pub length: Real,
+    }
+
+    impl ParticleRod {
+        /// Creates a new rod between the two given particles.
+        pub fn new<T: AsReal>(particles: [usize; 2], length: T) -> Self {
+            ParticleRod { particles, length: length.as_real() }
+        }
+    }
+
+    impl ParticleContactGenerator for ParticleRod {
+        /// Fills the given contact list with the contact needed to
+        /// keep the rod from extending or compressing.
+        fn add_contact(&mut self, particles: &[Particle], contacts: &mut Vec<ParticleContact>, limit: usize) -> usize {
+            if limit == 0 {
+                return 0;
+            }
+
+            // Find the length of the rod
+            let (normal, current_len) = direction_and_length(
+                particles[self.particles[0]].get_position(),
+                particles[self.particles[1]].get_position()
+            );
+
+            // Check if we're over-extended
+            if current_len == self.length {
+                return 0;
+            }
+
+            // The contact normal depends on whether we're extending
+            // or compressing
+            let (normal, penetration) = if current_len > self.length {
+                (normal, current_len - self.length)
+            } else {
+                (-normal, self.length - current_len)
+            };
+
+            // Always use zero restitution (no bounciness)
+            contacts.push(ParticleContact::new(
+                self.particles[0], Some(self.particles[1]), normal, penetration, 0
+            ));
+
+            1
+        }
+    }
+
+    /// Cable constraints link a particle to an anchor point, generating
+    /// a contact if they stray too far apart.
+    #[derive(Debug, Clone)]
+    pub struct ParticleCableConstraint {
+        /// Holds the particle connected by this constraint.
+        pub particle: usize,
+        /// The point to which the particle is anchored.
+        pub anchor: Vec3,
+        /// Holds the maximum length of the cable.
+        pub max_length: Real,
+        /// Holds the restitution (bounciness) of the cable.
+        pub restitution: Real,
+    }
+
+    impl ParticleCableConstraint {
+        /// Creates a new cable between the given particle and anchor.
+        pub fn new<T, U>(particle: usize, anchor: Vec3, max_length: T, restitution: U) -> Self
+        where T: AsReal, U: AsReal {
+            ParticleCableConstraint {
+                particle,
+                anchor,
+                max_length: max_length.as_real(),
+                restitution: restitution.as_real(),
+            }
+        }
+    }
+
+    impl ParticleContactGenerator for ParticleCableConstraint {
+        /// Fills the given contact list with the contact needed to
+        /// keep the cable from over-extending.
+        fn add_contact(&mut self, particles: &[Particle], contacts: &mut Vec<ParticleContact>, limit: usize) -> usize {
+            if limit == 0 {
+                return 0;
+            }
+
+            // Find the length of the cable
+            let (normal, length) = direction_and_length(
+                particles[self.particle].get_position(),
+                self.anchor
+            );
+
+            // Check if we're over-extended
+            if length < self.max_length {
+                return 0;
+            }
+
+            // Otherwise return the contact
+            let penetration = length - self.max_length;
+            contacts.push(ParticleContact::new(
+                self.particle, None, normal, penetration, self.restitution
+            ));
+
+            1
+        }
+    }
+
+    /// Rod constraints link a particle to an anchor point, generating
+    /// a contact if they stray too far apart or too close.
+    #[derive(Debug, Clone)]
+    pub struct ParticleRodConstraint {
+        /// Holds the particle connected by this constraint.
+        pub particle: usize,
+        /// The point to which the particle is anchored.
+        pub anchor: Vec3,
+        /// Holds the length of the rod.
+        pub length: Real,
+    }
+
+    impl ParticleRodConstraint {
+        /// Creates a new rod between the given particle and anchor.
+        pub fn new<T: AsReal>(particle: usize, anchor: Vec3, length: T) -> Self {
+            ParticleRodConstraint { particle, anchor, length: length.as_real() }
+        }
+    }
+
+    impl ParticleContactGenerator for ParticleRodConstraint {
+        /// Fills the given contact list with the contact needed to
+        /// keep the rod from extending or compressing.
+        fn add_contact(&mut self, particles: &[Particle], contacts: &mut Vec<ParticleContact>, limit: usize) -> usize {
+            if limit == 0 {
+                return 0;
+            }
+
+            // Find the length of the rod
+            let (normal, current_len) = direction_and_length(
+                particles[self.particle].get_position(),
+                self.anchor
+            );
+
+            // Check if we're over-extended
+            if current_len == self.length {
+                return 0;
+            }
+
+            // The contact normal depends on whether we're extending
+            // or compressing
+            let (normal, penetration) = if current_len > self.length {
+                (normal, current_len - self.length)
+            } else {
+                (-normal, self.length - current_len)
+            };
+
+            // Always use zero restitution (no bounciness)
+            contacts.push(ParticleContact::new(
+                self.particle, None, normal, penetration, 0
+            ));
+
+            1
+        }
+    }
+}
+
+pub mod particle_world {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use super::precision::*;
+    use super::core::*;
+    use super::particle::*;
+    use super::particle_forces::*;
+    use super::particle_contacts::*;
+    use super::clock::*;
+    use super::collide_coarse::*;
+    use super::query::*;
+
+    /// Keeps track of a set of particles, and provides the means to
+    /// update them all.
+    pub struct ParticleWorld {
+        /// Holds the particles simulated by this world.
+        particles: Vec<Particle>,
+        /// Holds the force generators for the particles in this world.
+        registry: ParticleForceRegistry,
+        /// Holds the resolver for contacts.
+        resolver: ParticleContactResolver,
+        /// Holds the contact generators.
+        contact_generators: Vec<Rc<RefCell<dyn ParticleContactGenerator>>>,
+        /// Holds the list of contacts generated in the last frame.
+        contacts: Vec<ParticleContact>,
+        /// Holds the maximum number of contacts allowed.
+        max_contacts: usize,
+        /// True if the world should calculate the number of iterations
+        /// to give the contact resolver at each frame.
+        calculate_iterations: bool,
+        /// Holds the integrator used for all the particles, overriding
+        /// their own one when set.
+        integrator: Option<Integrator>,
+        /// Holds the state of each particle before the last step.
+        previous_states: Vec<ParticleState>,
+        /// Holds the broad phase used to answer the queries.
+        broad_phase: Rc<RefCell<dyn BroadPhase>>,
+        /// True if the particles may have moved since the broad phase
+        /// was last updated. The broad phase is only updated when a
+        /// query needs it, so worlds without queries don't pay for it.
+        broad_phase_dirty: Cell<bool>,
+        /// Holds the radius of the particles, as seen by the queries.
+        particle_radius: Real,
+        /// Holds the bounding boxes of the particles, kept between
+        /// updates to avoid reallocating them.
+        volumes: RefCell<Vec<Aabb>>,
+        /// Holds the layers of each particle, as a bit mask.
+        layers: Vec<u32>,
+    }
+
+    /// The layer particles are added to.
+    pub const DEFAULT_LAYER: u32 = 1;
+
+    /// The layer mask letting the queries see every particle.
+    pub const ALL_LAYERS: u32 = u32::MAX;
+
+    impl ParticleWorld {
+        /// Creates a new particle simulator that can handle up to the
+        /// given number of contacts per frame. You can also optionally
+        /// give a number of contact-resolution iterations to use. If you
+        /// pass zero, then twice the number of contacts will be used.
+        pub fn new(max_contacts: usize, iterations: usize) -> Self {
+            ParticleWorld {
+                particles: Vec::new(),
+                registry: ParticleForceRegistry::new(),
+                resolver: ParticleContactResolver::new(iterations),
+                contact_generators: Vec::new(),
+                contacts: Vec::with_capacity(max_contacts),
+                max_contacts,
+                calculate_iterations: iterations == 0,
+                integrator: None,
+                previous_states: Vec::new(),
+                broad_phase: Rc::new(RefCell::new(BruteForceBroadPhase::new())),
+                broad_phase_dirty: Cell::new(false),
+                particle_radius: 0 as Real,
+                volumes: RefCell::new(Vec::new()),
+                layers: Vec::new(),
+            }
+        }
+
+        /// Sets the integrator used for all the particles of the world.
+        /// If `None`, each particle is integrated with its own integrator.
+        pub fn set_integrator(&mut self, integrator: Option<Integrator>) {
+            self.integrator = integrator;
+        }
+
+        /// Returns the integrator used for all the particles, if any.
+        pub fn get_integrator(&self) -> Option<Integrator> {
+            self.integrator
+        }
+
+        /// Adds a particle to the world, returning its index.
+        pub fn add_particle(&mut self, particle: Particle) -> usize {
+            self.previous_states.push(particle.get_state());
+            self.layers.push(DEFAULT_LAYER);
+            self.particles.push(particle);
+            self.broad_phase_dirty.set(true);
+            self.particles.len() - 1
+        }
+
+        /// Returns the particles simulated by this world.
+        pub fn get_particles(&self) -> &[Particle] {
+            &self.particles
+        }
+
+        /// Returns the particles simulated by this world, mutably.
+        pub fn get_particles_mut(&mut self) -> &mut [Particle] {
+            self.broad_phase_dirty.set(true);
+            &mut self.particles
+        }
+
+        /// Returns the particle at the given index.
+        pub fn get_particle(&self, index: usize) -> &Particle {
+            &self.particles[index]
+        }
+
+        /// Returns the particle at the given index, mutably.
+        pub fn get_particle_mut(&mut self, index: usize) -> &mut Particle {
+            self.broad_phase_dirty.set(true);
+            &mut self.particles[index]
+        }
+
+        /// Returns the force registry of the world.
+        pub fn get_force_registry(&self) -> &ParticleForceRegistry {
+            &self.registry
+        }
+
+        /// Returns the force registry of the world, mutably.
+        pub fn get_force_registry_mut(&mut self) -> &mut ParticleForceRegistry {
+            &mut self.registry
+        }
+
+        /// Adds a contact generator to be called at each frame.
+        pub fn add_contact_generator(&mut self, generator: Rc<RefCell<dyn ParticleContactGenerator>>) {
+            self.contact_generators.push(generator);
+        }
+
+        /// Removes the given contact generator from the world. If the
+        /// generator is not registered, this method will have no effect.
+        pub fn remove_contact_generator(&mut self, generator: &Rc<RefCell<dyn ParticleContactGenerator>>) {
+            self.contact_generators.retain(|registered| !Rc::ptr_eq(registered, generator));
+        }
+
+        /// Returns the contacts generated in the last frame.
+        pub fn get_contacts(&self) -> &[ParticleContact] {
+            &self.contacts
+        }
+
+        /// Calls each of the registered contact generators to report
+        /// their contacts, and returns the number of generated contacts.
+        pub fn generate_contacts(&mut self) -> usize {
+            self.contacts.clear();
+
+            // Generators may update a broad phase shared with the queries
+            self.broad_phase_dirty.set(true);
+
+            for generator in self.contact_generators.iter() {
+                let limit = self.max_contacts - self.contacts.len();
+                generator.borrow_mut().add_contact(&self.particles, &mut self.contacts, limit);
+
+                // We've run out of contacts to fill. This means we're
+                // missing contacts.
+                if self.contacts.len() >= self.max_contacts {
+                    self.contacts.truncate(self.max_contacts);
+                    break;
+                }
+            }
+
+            self.contacts.len()
+        }
+
+        /// Initializes the world for a simulation frame. This clears
+        /// the force accumulators for particles in the world. After
+        /// calling this, the particles can have their forces for this
+        /// frame added.
+        pub fn start_frame(&mut self) {
+            for particle in self.particles.iter_mut() {
+                particle.clear_accumulator();
+            }
+        }
+
+        /// Integrates all the particles in this world forward in time
+        /// by the given duration.
+        pub fn integrate<T: AsReal>(&mut self, duration: T) {
+            let duration = duration.as_real();
+            self.broad_phase_dirty.set(true);
+
+            for particle in self.particles.iter_mut() {
+                match self.integrator {
+                    Some(integrator) => particle.integrate_with(integrator, duration),
+                    None => particle.integrate(duration),
+                }
+            }
+        }
+
+        /// Processes all the physics for the particle world: records
+        /// the current state of the particles, applies the force
+        /// generators, integrates the particles and then generates and
+        /// resolves their contacts.
+        pub fn run_physics<T: AsReal>(&mut self, duration: T) {
+            let duration = duration.as_real();
+
+            // Remember where the particles were, for interpolation
+            for (state, particle) in self.previous_states.iter_mut().zip(self.particles.iter()) {
+                *state = particle.get_state();
+            }
+
+            // First apply the force generators
+            self.registry.update_forces(&mut self.particles, duration);
+
+            // Then integrate the objects
+            self.integrate(duration);
+
+            // Generate contacts
+            let used_contacts = self.generate_contacts();
+
+            // And process them
+            if used_contacts > 0 {
+                if self.calculate_iterations {
+                    self.resolver.set_iterations(used_contacts * 2);
+                }
+
+                self.resolver.resolve_contacts(&mut self.contacts, &mut self.particles, duration);
+            }
+
+            // The queries will need the new positions
+            self.broad_phase_dirty.set(true);
+        }
+
+        /// Runs a whole simulation step of the given duration: starts
+        /// the frame and runs the physics.
+        pub fn step<T: AsReal>(&mut self, duration: T) {
+            self.start_frame();
+            self.run_physics(duration);
+        }
+
+        /// Advances the world by the given real time, in fixed steps
+        /// given by the clock. Returns the number of steps taken.
+        pub fn advance<T: AsReal>(&mut self, clock: &mut SimulationClock, frame_time: T) -> u32 {
+            clock.advance(frame_time, |duration| self.step(duration))
+        }
+
+        /// Returns the state of the particle at the given index before
+        /// the last step.
+        pub fn get_previous_state(&self, index: usize) -> ParticleState {
+            self.previous_states[index]
+        }
+
+        /// Returns the state of the particle at the given index,
+        /// interpolated between the last two steps by `alpha`, which
+        /// is usually given by `SimulationClock::alpha`.
+        pub fn get_interpolated_state<T: AsReal>(&self, index: usize, alpha: T) -> ParticleState {
+            self.previous_states[index].interpolate(&self.particles[index].get_state(), alpha)
+        }
+
+        /// Sets the broad phase used to answer the queries, which is
+        /// brute force by default.
+        ///
+        /// Giving the same broad phase to a `ParticleCollider` lets one
+        /// structure serve both the contacts and the queries, instead of
+        /// keeping two of them over the same particles. The queries then
+        /// update it again with the positions after contact resolution,
+        /// which is cheap for incremental broad phases as long as the
+        /// particle radius matches the radius of the collider.
+        pub fn set_broad_phase(&mut self, broad_phase: Rc<RefCell<dyn BroadPhase>>) {
+            self.broad_phase = broad_phase;
+            self.broad_phase_dirty.set(true);
+        }
+
+        /// Returns the broad phase used to answer the queries.
+        pub fn get_broad_phase(&self) -> &Rc<RefCell<dyn BroadPhase>> {
+            &self.broad_phase
+        }
+
+        /// Sets the radius of the particles, as seen by the queries.
+        pub fn set_particle_radius<T: AsReal>(&mut self, particle_radius: T) {
+            self.particle_radius = particle_radius.as_real();
+            self.broad_phase_dirty.set(true);
+        }
+
+        /// Returns the radius of the particles, as seen by the queries.
+        pub fn get_particle_radius(&self) -> Real {
+            self.particle_radius
+        }
+
+        /// Updates the broad phase with the current positions of the
+        /// particles, if they may have moved since the last update.
+        /// Queries do this on their own, so this is only useful to pay
+        /// for the update at a chosen time.
+        pub fn update_broad_phase(&self) {
+            if !self.broad_phase_dirty.replace(false) {
+                return;
+            }
+
+            let half_size = Vec3::new(self.particle_radius, self.particle_radius, self.particle_radius);
+            let mut volumes = self.volumes.borrow_mut();
+
+            volumes.clear();
+            volumes.extend(self.particles.iter().map(|particle| {
+                Aabb::from_centre_and_half_size(particle.get_position(), half_size)
+            }));
+            self.broad_phase.borrow_mut().update(&volumes);
+        }
+
+        /// Sets the layers of the particle at the given index, as a bit
+        /// mask. Particles start in the default layer.
+        pub fn set_particle_layer(&mut self, index: usize, layer: u32) {
+            self.layers[index] = layer;
+        }
+
+        /// Returns the layers of the particle at the given index.
+        pub fn get_particle_layer(&self, index: usize) -> u32 {
+            self.layers[index]
+        }
+
+        /// Returns the particles whose bounding boxes overlap the given
+        /// box and that are in one of the layers of the given mask.
+        fn query_candidates(&self, volume: &Aabb, layer_mask: u32) -> impl Iterator<Item = usize> + '_ {
+            self.update_broad_phase();
+
+            self.broad_phase.borrow().query(volume).into_iter()
+                .filter(move |index| self.layers[*index] & layer_mask != 0)
+        }
+
+        /// Returns the closest particle hit by the ray up to the given
+        /// distance, among the layers of the given mask.
+        pub fn raycast_closest<T: AsReal>(&self, ray: &Ray, max_distance: T, layer_mask: u32) -> Option<RaycastHit> {
+            self.raycast_all(ray, max_distance, layer_mask).into_iter().next()
+        }
+
+        /// Returns all the particles hit by the ray up to the given
+        /// distance among the layers of the given mask, from the closest
+        /// to the furthest.
+        pub fn raycast_all<T: AsReal>(&self, ray: &Ray, max_distance: T, layer_mask: u32) -> Vec<RaycastHit> {
+            let max_distance = max_distance.as_real();
+
+            let mut hits: Vec<RaycastHit> = self.query_candidates(&ray.get_bounds(max_distance), layer_mask)
+                .filter_map(|index| {
+                    raycast_particle(ray, &self.particles[index], index, self.particle_radius, max_distance)
+                })
+                .collect();
+
+            hits.sort_by(|one, two| one.distance.total_cmp(&two.distance));
+            hits
+        }
+
+        /// Sweeps a sphere of the given radius along the ray, returning
+        /// the first particle it touches up to the given distance, among
+        /// the layers of the given mask.
+        pub fn sphere_cast<T, U>(&self, ray: &Ray, radius: T, max_distance: U, layer_mask: u32) -> Option<RaycastHit>
+        where T: AsReal, U: AsReal {
+            let radius = radius.as_real();
+            let max_distance = max_distance.as_real();
+
+            // The swept sphere covers the bounds of the ray grown by its radius
+            let bounds = ray.get_bounds(max_distance);
+            let half_size = Vec3::new(radius, radius, radius);
+            let bounds = Aabb::new(bounds.get_min() - half_size, bounds.get_max() + half_size);
+
+            self.query_candidates(&bounds, layer_mask)
+                .filter_map(|index| {
+                    sphere_cast_particle(ray, radius, &self.particles[index], index, self.particle_radius, max_distance)
+                })
+                .min_by(|one, two| one.distance.total_cmp(&two.distance))
+        }
+
+        /// Returns the particles overlapping the sphere of the given centre
+        /// and radius, among the layers of the given mask.
+        pub fn overlap_sphere<T: AsReal>(&self, centre: Vec3, radius: T, layer_mask: u32) -> Vec<usize> {
+            let radius = radius.as_real();
+            let bounds = Aabb::from_centre_and_half_size(centre, Vec3::new(radius, radius, radius));
+            let reach = radius + self.particle_radius;
+
+            let mut indices: Vec<usize> = self.query_candidates(&bounds, layer_mask)
+                .filter(|index| (self.particles[*index].get_position() - centre).square_magnitude() <= reach * reach)
+                .collect();
+
+            indices.sort_unstable();
+            indices
+        }
+
+        /// Returns the particles overlapping the given box, among the
+        /// layers of the given mask.
+        pub fn overlap_aabb(&self, volume: &Aabb, layer_mask: u32) -> Vec<usize> {
+            let mut indices: Vec<usize> = self.query_candidates(volume, layer_mask)
+                .filter(|index| particle_overlaps_aabb(&self.particles[*index], self.particle_radius, volume))
+                .collect();
+
+            indices.sort_unstable();
+            indices
+        }
+
+        /// Returns the particles containing the given point, among the
+        /// layers of the given mask.
+        pub fn point_inside(&self, point: Vec3, layer_mask: u32) -> Vec<usize> {
+            self.overlap_sphere(point, 0, layer_mask)
+        }
+    }
+}
+
+pub mod clock {
+    use super::precision::*;
+
+    /// Runs a simulation in fixed time steps, decoupled from the frame
+    /// rate. The real time of each frame is accumulated and consumed in
+    /// steps of the same duration, so the results are reproducible.
+    #[derive(Debug, Clone)]
+    pub struct SimulationClock {
+        /// Holds the duration of each simulation step.
+        step: Real,
+        /// Holds the real time not yet consumed by the simulation.
+        accumulator: Real,
+        /// Holds the maximum number of steps taken in a single frame.
+        max_steps: u32,
+        /// Holds the total simulated time.
+        time: Real,
+    }
+
+    impl SimulationClock {
+        /// Creates a new clock with the given step duration, which will
+        /// not run more than `max_steps` steps in a single frame.
+        pub fn new<T: AsReal>(step: T, max_steps: u32) -> Self {
+            let step = step.as_real();
+
+            if step <= 0 as Real {
+                panic!("The step duration must be positive!");
+            }
+
+            SimulationClock { step, accumulator: 0 as Real, max_steps, time: 0 as Real }
+        }
+
+        /// Accumulates the given real time and runs as many fixed steps
+        /// as fit in it, calling `step_fn` with the step duration for
+        /// each one. Returns the number of steps taken.
+        ///
+        /// If the steps are capped by `max_steps`, the remaining time is
+        /// dropped so that a slow frame does not snowball into the next.
+        pub fn advance<T, F>(&mut self, frame_time: T, mut step_fn: F) -> u32
+        where T: AsReal, F: FnMut(Real) {
+            let frame_time = frame_time.as_real();
+
+            if frame_time > 0 as Real {
+                self.accumulator += frame_time;
+            }
+
+            let mut steps = 0;
+
+            while self.accumulator >= self.step && steps < self.max_steps {
+                step_fn(self.step);
+                self.accumulator -= self.step;
+                self.time += self.step;
+                steps += 1;
+            }
+
+            // Drop the time we are not able to catch up with
+            if self.accumulator >= self.step {
+                self.accumulator %= self.step;
+            }
+
+            steps
+        }
+
+        /// Returns how far the accumulated time is into the next step,
+        /// from zero to one, to interpolate the rendered state.
+        pub fn alpha(&self) -> Real {
+            self.accumulator / self.step
+        }
+
+        /// Returns the duration of each simulation step.
+        pub fn get_step(&self) -> Real {
+            self.step
+        }
+
+        /// Returns the maximum number of steps taken in a single frame.
+        pub fn get_max_steps(&self) -> u32 {
+            self.max_steps
+        }
+
+        /// Returns the total simulated time.
+        pub fn get_time(&self) -> Real {
+            self.time
+        }
+    }
+}
+
+pub mod rigid_body {
+    use super::precision::*;
+    use super::core::*;
+    use super::particle::{DEFAULT_SLEEP_EPSILON, recency_weighted_motion};
+
+    /// A rigid body is the basic simulation object in the physics
+    /// core. It has position and orientation data, along with first
+    /// derivatives, and can be integrated forward through time.
+    #[derive(Debug, Clone)]
+    pub struct RigidBody {
+        /// Holds the inverse of the mass of the rigid body.
+        inverse_mass: Real,
+        /// Holds the inverse of the body's inertia tensor, given in
+        /// body space.
+        inverse_inertia_tensor: Matrix3,
+        /// Holds the amount of damping applied to linear motion.
+        linear_damping: Real,
+        /// Holds the amount of damping applied to angular motion.
+        angular_damping: Real,
+        /// Holds the linear position of the rigid body in world space.
+        position: Vec3,
+        /// Holds the angular orientation of the rigid body in world space.
+        orientation: Quaternion,
+        /// Holds the linear velocity of the rigid body in world space.
+        velocity: Vec3,
+        /// Holds the angular velocity, or rotation, of the rigid body
+        /// in world space.
+        rotation: Vec3,
+        /// Holds the inverse inertia tensor of the body in world space.
+        inverse_inertia_tensor_world: Matrix3,
+        /// Holds a transform matrix for converting body space into
+        /// world space and vice versa.
+        transform_matrix: Matrix4,
+        /// Holds the accumulated force to be applied at the next
+        /// integration step.
+        force_accum: Vec3,
+        /// Holds the accumulated torque to be applied at the next
+        /// integration step.
+        torque_accum: Vec3,
+        /// Holds the acceleration of the rigid body, which can be used
+        /// to set acceleration due to gravity or any other constant
+        /// acceleration.
+        acceleration: Vec3,
+        /// Holds the linear acceleration of the rigid body for the
+        /// previous frame.
+        last_frame_acceleration: Vec3,
+        /// A body can be put to sleep to avoid it being updated
+        /// by the integration functions or affected by collisions
+        /// with the world.
+        is_awake: bool,
+        /// Some bodies may never be allowed to fall asleep. User
+        /// controlled bodies, for example, should be always awake.
+        can_sleep: bool,
+        /// Holds the amount of motion of the body. This is a recency
+        /// weighted mean that can be used to put a body to sleep.
+        motion: Real,
+        /// Holds the motion below which the body is put to sleep.
+        sleep_epsilon: Real,
+    }
+
+    impl Default for RigidBody {
+        /// Returns a body of unit mass and identity inertia tensor at
+        /// rest at the origin.
+        fn default() -> Self {
+            Self::new(1, Matrix3::default())
+        }
+    }
+
+    impl RigidBody {
+        /// Creates a new body at rest at the origin, with the given mass
+        /// and inertia tensor (in body space).
+        pub fn new<T: AsReal>(mass: T, inertia_tensor: Matrix3) -> Self {
+            let mut body = RigidBody {
+                inverse_mass: 1 as Real,
+                inverse_inertia_tensor: Matrix3::default(),
+                linear_damping: 1 as Real,
+                angular_damping: 1 as Real,
+                position: Vec3::default(),
+                orientation: Quaternion::default(),
+                velocity: Vec3::default(),
+                rotation: Vec3::default(),
+                inverse_inertia_tensor_world: Matrix3::default(),
+                transform_matrix: Matrix4::default(),
+                force_accum: Vec3::default(),
+                torque_accum: Vec3::default(),
+                acceleration: Vec3::default(),
+                last_frame_acceleration: Vec3::default(),
+                is_awake: true,
+                can_sleep: true,
+                motion: DEFAULT_SLEEP_EPSILON * 2.0,
+                sleep_epsilon: DEFAULT_SLEEP_EPSILON,
+            };
+
+            body.set_mass(mass);
+            body.set_inertia_tensor(inertia_tensor);
+            body
+        }
+
+        /// Creates an immovable body (with infinite mass and inertia)
+        /// at the given position and orientation.
+        pub fn new_static(position: Vec3, orientation: Quaternion) -> Self {
+            let mut body = Self::default();
+            body.set_inverse_mass(0);
+            body.set_inverse_inertia_tensor(Matrix3::zero());
+            body.position = position;
+            body.set_orientation(orientation);
+            body
+        }
+
+        /// Calculates internal data from state data. This is called
+        /// automatically by the setters of the state, and after each
+        /// integration step.
+        pub fn calculate_derived_data(&mut self) {
+            self.orientation.normalize();
+
+            // Calculate the transform matrix for the body.
+            self.transform_matrix = Matrix4::from_orientation_and_pos(self.orientation, self.position);
+
+            // Calculate the inertia tensor in world space.
+            let rotation = self.transform_matrix.get_rotation();
+            self.inverse_inertia_tensor_world = rotation * self.inverse_inertia_tensor * rotation.transpose();
+        }
+
+        /// Integrates the rigid body forward in time by the given amount.
+        /// Bodies with infinite mass or asleep are never moved.
+        pub fn integrate<T: AsReal>(&mut self, duration: T) {
+            let duration = duration.as_real();
+
+            // We don't integrate things with infinite mass, and sleeping
+            // bodies ignore the forces applied to them.
+            if !self.has_finite_mass() || !self.is_awake || duration <= 0 as Real {
+                self.clear_accumulators();
+                return;
+            }
+
+            // Calculate linear acceleration from force inputs.
+            self.last_frame_acceleration = self.acceleration;
+            self.last_frame_acceleration.add_scaled_vector(self.force_accum, self.inverse_mass);
+
+            // Calculate angular acceleration from torque inputs.
+            let angular_acceleration = self.inverse_inertia_tensor_world * self.torque_accum;
+
+            // Adjust velocities
+            // Update linear velocity from both acceleration and impulse.
+            self.velocity.add_scaled_vector(self.last_frame_acceleration, duration);
+
+            // Update angular velocity from both acceleration and impulse.
+            self.rotation.add_scaled_vector(angular_acceleration, duration);
+
+            // Impose drag.
+            self.velocity *= self.linear_damping.powf(duration);
+            self.rotation *= self.angular_damping.powf(duration);
+
+            // Adjust positions
+            // Update linear position.
+            self.position.add_scaled_vector(self.velocity, duration);
+
+            // Update angular position.
+            self.orientation.add_scaled_vector(self.rotation, duration);
+
+            // Normalise the orientation, and update the matrices with
+            // the new position and orientation
+            self.calculate_derived_data();
+
+            // Clear accumulators.
+            self.clear_accumulators();
+
+            // Update the kinetic energy store, and possibly put the body to
+            // sleep.
+            if self.can_sleep {
+                let current_motion = self.velocity.square_magnitude() + self.rotation.square_magnitude();
+                self.motion = recency_weighted_motion(self.motion, current_motion, duration);
+
+                if self.motion < self.sleep_epsilon {
+                    self.set_awake(false);
+                } else if self.motion > 10.0 * self.sleep_epsilon {
+                    self.motion = 10.0 * self.sleep_epsilon;
+                }
+            }
+        }
+
+        /// Returns true if the body is awake and responding to
+        /// integration.
+        pub fn is_awake(&self) -> bool {
+            self.is_awake
+        }
+
+        /// Sets the awake state of the body. If the body is put to sleep,
+        /// it will not integrate its motion, and its velocity and rotation
+        /// are removed. Adding forces does not wake a body up.
+        pub fn set_awake(&mut self, awake: bool) {
+            if awake {
+                self.is_awake = true;
+
+                // Add a bit of motion to avoid it falling asleep immediately.
+                self.motion = self.sleep_epsilon * 2.0;
+            } else {
+                self.is_awake = false;
+                self.velocity = Vec3::default();
+                self.rotation = Vec3::default();
+            }
+        }
+
+        /// Returns true if the body is allowed to fall asleep.
+        pub fn get_can_sleep(&self) -> bool {
+            self.can_sleep
+        }
+
+        /// Sets whether the body is ever allowed to go to sleep. A
+        /// sleeping body that can no longer sleep is woken up.
+        pub fn set_can_sleep(&mut self, can_sleep: bool) {
+            self.can_sleep = can_sleep;
+
+            if !can_sleep && !self.is_awake {
+                self.set_awake(true);
+            }
+        }
+
+        /// Returns the recency-weighted average of the squared linear
+        /// and angular speed of the body.
+        pub fn get_motion(&self) -> Real {
+            self.motion
+        }
+
+        /// Sets the motion below which the body is put to sleep.
+        pub fn set_sleep_epsilon<T: AsReal>(&mut self, sleep_epsilon: T) {
+            self.sleep_epsilon = sleep_epsilon.as_real();
+        }
+
+        /// Returns the motion below which the body is put to sleep.
+        pub fn get_sleep_epsilon(&self) -> Real {
+            self.sleep_epsilon
+        }
+
+        /// Sets the mass of the rigid body, which can not be zero. Use
+        /// `set_inverse_mass(0)` to make the body immovable.
+        pub fn set_mass<T: AsReal>(&mut self, mass: T) {
+            let mass = mass.as_real();
+
+            if mass == 0 as Real {
+                panic!("Mass can not be zero!");
+            }
+
+            self.inverse_mass = 1.0 / mass;
+        }
+
+        /// Returns the mass of the rigid body, or the maximum real
+        /// value if the body has infinite mass.
+        pub fn get_mass(&self) -> Real {
+            if self.inverse_mass == 0 as Real {
+                REAL_MAX
+            } else {
+                1.0 / self.inverse_mass
+            }
+        }
+
+        /// Sets the inverse mass of the rigid body. A value of zero
+        /// makes the body immovable (infinite mass).
+        pub fn set_inverse_mass<T: AsReal>(&mut self, inverse_mass: T) {
+            self.inverse_mass = inverse_mass.as_real();
+        }
+
+        /// Returns the inverse mass of the rigid body.
+        pub fn get_inverse_mass(&self) -> Real {
+            self.inverse_mass
+        }
+
+        /// Returns true if the mass of the body is not infinite.
+        pub fn has_finite_mass(&self) -> bool {
+            self.inverse_mass > 0 as Real
+        }
+
+        /// Sets the inertia tensor for the rigid body, given in body
+        /// space. The tensor must be invertible, otherwise the inverse
+        /// inertia tensor is left unchanged.
+        pub fn set_inertia_tensor(&mut self, inertia_tensor: Matrix3) {
+            if let Some(inverse) = inertia_tensor.inverse() {
+                self.set_inverse_inertia_tensor(inverse);
+            }
+        }
+
+        /// Returns the inertia tensor of the body in body space, or a
+        /// zero matrix if the body can not rotate.
+        pub fn get_inertia_tensor(&self) -> Matrix3 {
+            self.inverse_inertia_tensor.inverse().unwrap_or_else(Matrix3::zero)
+        }
+
+        /// Returns the inertia tensor of the body in world space, or a
+        /// zero matrix if the body can not rotate.
+        pub fn get_inertia_tensor_world(&self) -> Matrix3 {
+            self.inverse_inertia_tensor_world.inverse().unwrap_or_else(Matrix3::zero)
+        }
+
+        /// Sets the inverse inertia tensor for the rigid body, given in
+        /// body space. A zero matrix makes the body unable to rotate.
+        pub fn set_inverse_inertia_tensor(&mut self, inverse_inertia_tensor: Matrix3) {
+            self.inverse_inertia_tensor = inverse_inertia_tensor;
+            self.calculate_derived_data();
+        }
+
+        /// Returns the inverse inertia tensor of the body in body space.
+        pub fn get_inverse_inertia_tensor(&self) -> Matrix3 {
+            self.inverse_inertia_tensor
+        }
+
+        /// Returns the inverse inertia tensor of the body in world space.
+        pub fn get_inverse_inertia_tensor_world(&self) -> Matrix3 {
+            self.inverse_inertia_tensor_world
+        }
+
+        /// Sets both linear and angular damping in one function call.
+        pub fn set_damping<T, U>(&mut self, linear_damping: T, angular_damping: U)
+        where T: AsReal, U: AsReal {
+            self.linear_damping = linear_damping.as_real();
+            self.angular_damping = angular_damping.as_real();
+        }
+
+        /// Sets the linear damping for the rigid body.
+        pub fn set_linear_damping<T: AsReal>(&mut self, linear_damping: T) {
+            self.linear_damping = linear_damping.as_real();
+        }
+
+        /// Returns the current linear damping value.
+        pub fn get_linear_damping(&self) -> Real {
+            self.linear_damping
+        }
+
+        /// Sets the angular damping for the rigid body.
+        pub fn set_angular_damping<T: AsReal>(&mut self, angular_damping: T) {
+            self.angular_damping = angular_damping.as_real();
+        }
+
+        /// Returns the current angular damping value.
+        pub fn get_angular_damping(&self) -> Real {
+            self.angular_damping
+        }
+
+        /// Sets the position of the rigid body.
+        pub fn set_position(&mut self, position: Vec3) {
+            self.position = position;
+            self.calculate_derived_data();
+        }
+
+        /// Returns the position of the rigid body.
+        pub fn get_position(&self) -> Vec3 {
+            self.position
+        }
+
+        /// Sets the orientation of the rigid body. The given orientation
+        /// is normalised before being stored.
+        pub fn set_orientation(&mut self, orientation: Quaternion) {
+            self.orientation = orientation;
+            self.calculate_derived_data();
+        }
+
+        /// Returns the orientation of the rigid body.
+        pub fn get_orientation(&self) -> Quaternion {
+            self.orientation
+        }
+
+        /// Returns the transform matrix of the rigid body, converting
+        /// body space into world space.
+        pub fn get_transform(&self) -> Matrix4 {
+            self.transform_matrix
+        }
+
+        /// Converts the given point from world space into body space.
+        pub fn get_point_in_local_space(&self, point: Vec3) -> Vec3 {
+            self.transform_matrix.transform_inverse(point)
+        }
+
+        /// Converts the given point from body space into world space.
+        pub fn get_point_in_world_space(&self, point: Vec3) -> Vec3 {
+            self.transform_matrix.transform(point)
+        }
+
+        /// Converts the given direction from world space into body space.
+        pub fn get_direction_in_local_space(&self, direction: Vec3) -> Vec3 {
+            self.transform_matrix.transform_inverse_direction(direction)
+        }
+
+        /// Converts the given direction from body space into world space.
+        pub fn get_direction_in_world_space(&self, direction: Vec3) -> Vec3 {
+            self.transform_matrix.transform_direction(direction)
+        }
+
+        /// Sets the velocity of the rigid body, given in world space.
+        pub fn set_velocity(&mut self, velocity: Vec3) {
+            self.velocity = velocity;
+        }
+
+        /// Returns the velocity of the rigid body, in world space.
+        pub fn get_velocity(&self) -> Vec3 {
+            self.velocity
+        }
+
+        /// Applies the given change in velocity.
+        pub fn add_velocity(&mut self, delta_velocity: Vec3) {
+            self.velocity += delta_velocity;
+        }
+
+        /// Sets the rotation (angular velocity) of the rigid body, given
+        /// in world space.
+        pub fn set_rotation(&mut self, rotation: Vec3) {
+            self.rotation = rotation;
+        }
+
+        /// Returns the rotation (angular velocity) of the rigid body,
+        /// in world space.
+        pub fn get_rotation(&self) -> Vec3 {
+            self.rotation
+        }
+
+        /// Applies the given change in rotation.
+        pub fn add_rotation(&mut self, delta_rotation: Vec3) {
+            self.rotation += delta_rotation;
+        }
+
+        /// Sets the constant acceleration of the rigid body.
+        pub fn set_acceleration(&mut self, acceleration: Vec3) {
+            self.acceleration = acceleration;
+        }
+
+        /// Returns the constant acceleration of the rigid body.
+        pub fn get_acceleration(&self) -> Vec3 {
+            self.acceleration
+        }
+
+        /// Returns the linear acceleration of the body in the last
+        /// integration step.
+        pub fn get_last_frame_acceleration(&self) -> Vec3 {
+            self.last_frame_acceleration
+        }
+
+        /// Clears the forces and torques in the accumulators. This will
+        /// be called automatically after each integration step.
+        pub fn clear_accumulators(&mut self) {
+            self.force_accum = Vec3::default();
+            self.torque_accum = Vec3::default();
+        }
+
+        /// Adds the given force to the centre of mass of the rigid body.
+        /// The force is expressed in world coordinates. This wakes the
+        /// body up.
+        pub fn add_force(&mut self, force: Vec3) {
+            self.force_accum += force;
+            self.wake_up();
+        }
+
+        /// Adds the given force to the given point on the rigid body.
+        /// Both the force and the application point are given in world
+        /// space. Because the force is not applied at the centre of
+        /// mass, it may be split into both a force and a torque.
+        pub fn add_force_at_point(&mut self, force: Vec3, point: Vec3) {
+            // Convert to coordinates relative to center of mass.
+            let pt = point - self.position;
+
+            self.force_accum += force;
+            self.torque_accum += pt.vec_prod(force);
+            self.wake_up();
+        }
+
+        /// Adds the given force to the given point on the rigid body.
+        /// The direction of the force is given in world coordinates,
+        /// but the application point is given in body space.
+        pub fn add_force_at_body_point(&mut self, force: Vec3, point: Vec3) {
+            // Convert to coordinates relative to center of mass.
+            let pt = self.get_point_in_world_space(point);
+            self.add_force_at_point(force, pt);
+        }
+
+        /// Adds the given torque to the rigid body, expressed in world
+        /// coordinates. This wakes the body up.
+        pub fn add_torque(&mut self, torque: Vec3) {
+            self.torque_accum += torque;
+            self.wake_up();
+        }
+
+        /// Wakes the body up if it is asleep, so that the forces applied
+        /// to it are not lost.
+        fn wake_up(&mut self) {
+            if !self.is_awake {
+                self.set_awake(true);
+            }
+        }
+
+        /// Returns the force accumulated for the next integration step.
+        pub fn get_force_accumulator(&self) -> Vec3 {
+            self.force_accum
+        }
+
+        /// Returns the torque accumulated for the next integration step.
+        pub fn get_torque_accumulator(&self) -> Vec3 {
+            self.torque_accum
+        }
+    }
+}
+
+pub mod forces {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::precision::*;
+    use super::core::*;
+    use super::rigid_body::*;
+
+    /// A force generator can be asked to add a force to one or more
+    /// rigid bodies.
+    pub trait ForceGenerator {
+        /// Calculates and updates the force applied to the body at the
+        /// given index of the bodies slice.
+        fn update_force(&mut self, bodies: &mut [RigidBody], index: usize, duration: Real);
+    }
+
+    /// A force generator that applies a gravitational force. One
+    /// instance can be used for multiple rigid bodies.
+    #[derive(Debug, Clone)]
+    pub struct Gravity {
+        /// Holds the acceleration due to gravity.
+        gravity: Vec3,
+    }
+
+    impl Gravity {
+        /// Creates the generator with the given acceleration.
+        pub fn new(gravity: Vec3) -> Self {
+            Gravity { gravity }
+        }
+    }
+
+    impl ForceGenerator for Gravity {
+        /// Applies the gravitational force to the given rigid body.
+        fn update_force(&mut self, bodies: &mut [RigidBody], index: usize, _duration: Real) {
+            let body = &mut bodies[index];
+
+            // Check that we do not have infinite mass
+            if !body.has_finite_mass() {
+                return;
+            }
+
+            // Apply the mass-scaled force to the body
+            let force = self.gravity * body.get_mass();
+            body.add_force(force);
+        }
+    }
+
+    /// A force generator that applies a spring force between points
+    /// of two rigid bodies.
+    #[derive(Debug, Clone)]
+    pub struct Spring {
+        /// The point of connection of the spring, in local coordinates.
+        connection_point: Vec3,
+        /// The index of the body at the other end of the spring.
+        other: usize,
+        /// The point of connection of the spring to the other object,
+        /// in that object's local coordinates.
+        other_connection_point: Vec3,
+        /// Holds the spring constant.
+        spring_constant: Real,
+        /// Holds the rest length of the spring.
+        rest_length: Real,
+    }
+
+    impl Spring {
+        /// Creates a new spring between the given local point of the
+        /// body it is registered to, and the given local point of the
+        /// body at the index `other`.
+        pub fn new<T, U>(connection_point: Vec3, other: usize, other_connection_point: Vec3, spring_constant: T, rest_length: U) -> Self
+        where T: AsReal, U: AsReal {
+            Spring {
+                connection_point,
+                other,
+                other_connection_point,
+                spring_constant: spring_constant.as_real(),
+                rest_length: rest_length.as_real(),
+            }
+        }
+    }
+
+    impl ForceGenerator for Spring {
+        /// Applies the spring force to the given rigid body.
+        fn update_force(&mut self, bodies: &mut [RigidBody], index: usize, _duration: Real) {
+            // Calculate the two ends in world space
+            let lws = bodies[index].get_point_in_world_space(self.connection_point);
+            let ows = bodies[self.other].get_point_in_world_space(self.other_connection_point);
+
+            // Calculate the vector of the spring
+            let mut force = lws - ows;
+
+            // Calculate the magnitude of the force
+            let magnitude = (force.magnitude() - self.rest_length) * self.spring_constant;
+
+            // Calculate the final force and apply it
+            force.normalize();
+            force *= -magnitude;
+            bodies[index].add_force_at_point(force, lws);
+        }
+    }
+
+    /// A force generator that applies an aerodynamic force.
+    #[derive(Debug, Clone)]
+    pub struct Aero {
+        /// Holds the aerodynamic tensor for the surface in body space.
+        tensor: Matrix3,
+        /// Holds the relative position of the aerodynamic surface in
+        /// body coordinates.
+        position: Vec3,
+        /// Holds the velocity of the wind, in world space.
+        windspeed: Vec3,
+    }
+
+    impl Aero {
+        /// Creates a new aerodynamic force generator with the given
+        /// properties.
+        pub fn new(tensor: Matrix3, position: Vec3, windspeed: Vec3) -> Self {
+            Aero { tensor, position, windspeed }
+        }
+
+        /// Sets the velocity of the wind, in world space.
+        pub fn set_windspeed(&mut self, windspeed: Vec3) {
+            self.windspeed = windspeed;
+        }
+
+        /// Returns the velocity of the wind, in world space.
+        pub fn get_windspeed(&self) -> Vec3 {
+            self.windspeed
+        }
+    }
+
+    /// Uses an explicit tensor matrix to update the force on the given
+    /// rigid body, for a surface at the given point in body space.
+    fn update_force_from_tensor(body: &mut RigidBody, tensor: Matrix3, position: Vec3, windspeed: Vec3) {
+        // Calculate total velocity (windspeed and body's velocity).
+        let mut velocity = body.get_velocity();
+        velocity += windspeed;
+
+        // Calculate the velocity in body coordinates
+        let body_vel = body.get_direction_in_local_space(velocity);
+
+        // Calculate the force in body coordinates
+        let body_force = tensor * body_vel;
+        let force = body.get_direction_in_world_space(body_force);
+
+        // Apply the force
+        body.add_force_at_body_point(force, position);
+    }
+
+    impl ForceGenerator for Aero {
+        /// Applies the aerodynamic force to the given rigid body.
+        fn update_force(&mut self, bodies: &mut [RigidBody], index: usize, _duration: Real) {
+            update_force_from_tensor(&mut bodies[index], self.tensor, self.position, self.windspeed);
+        }
+    }
+
+    /// A force generator with a control aerodynamic surface. This
+    /// requires three inertia tensors, for the two extremes and
+    /// 'resting' position of the control surface. The latter tensor is
+    /// the one inherited from the base `Aero` surface.
+    #[derive(Debug, Clone)]
+    pub struct AeroControl {
+        /// The aerodynamic surface at the resting position.
+        aero: Aero,
+        /// The aerodynamic tensor for the surface, when the control
+        /// is at its minimum value.
+        min_tensor: Matrix3,
+        /// The aerodynamic tensor for the surface, when the control
+        /// is at its maximum value.
+        max_tensor: Matrix3,
+        /// The current position of the control for this surface. This
+        /// should range between -1 (in which case the `min_tensor`
+        /// value is used), through 0 (where the base tensor is used)
+        /// to +1 (where the `max_tensor` value is used).
+        control_setting: Real,
+    }
+
+    impl AeroControl {
+        /// Creates a new aerodynamic control surface with the given
+        /// properties, with the control at its resting position.
+        pub fn new(base: Matrix3, min: Matrix3, max: Matrix3, position: Vec3, windspeed: Vec3) -> Self {
+            AeroControl {
+                aero: Aero::new(base, position, windspeed),
+                min_tensor: min,
+                max_tensor: max,
+                control_setting: 0 as Real,
+            }
+        }
+
+        /// Sets the control position of this control. This should range
+        /// between -1 (in which case the `min_tensor` value is used),
+        /// through 0 (where the base tensor is used) to +1 (where the
+        /// `max_tensor` value is used). Values outside that range give
+        /// undefined results.
+        pub fn set_control<T: AsReal>(&mut self, value: T) {
+            self.control_setting = value.as_real();
+        }
+
+        /// Returns the control position of this control.
+        pub fn get_control(&self) -> Real {
+            self.control_setting
+        }
+
+        /// Sets the velocity of the wind, in world space.
+        pub fn set_windspeed(&mut self, windspeed: Vec3) {
+            self.aero.set_windspeed(windspeed);
+        }
+
+        /// Calculates the final aerodynamic tensor for the current
+        /// control setting.
+        pub fn get_tensor(&self) -> Matrix3 {
+            if self.control_setting <= -1.0 {
+                self.min_tensor
+            } else if self.control_setting >= 1.0 {
+                self.max_tensor
+            } else if self.control_setting < 0 as Real {
+                Matrix3::linear_interpolate(self.min_tensor, self.aero.tensor, self.control_setting + 1.0)
+            } else if self.control_setting > 0 as Real {
+                Matrix3::linear_interpolate(self.aero.tensor, self.max_tensor, self.control_setting)
+            } else {
+                self.aero.tensor
+            }
+        }
+    }
+
+    impl ForceGenerator for AeroControl {
+        /// Applies the aerodynamic force to the given rigid body, using
+        /// the tensor of the current control setting.
+        fn update_force(&mut self, bodies: &mut [RigidBody], index: usize, _duration: Real) {
+            let tensor = self.get_tensor();
+            update_force_from_tensor(&mut bodies[index], tensor, self.aero.position, self.aero.windspeed);
+        }
+    }
+
+    /// A force generator to apply a buoyant force to a rigid body,
+    /// against a horizontal water plane.
+    #[derive(Debug, Clone)]
+    pub struct Buoyancy {
+        /// The maximum submersion depth of the object before it
+        /// generates its maximum buoyancy force.
+        max_depth: Real,
+        /// The volume of the object.
+        volume: Real,
+        /// The height of the water plane above y=0. The plane will be
+        /// parallel to the XZ plane.
+        water_height: Real,
+        /// The density of the liquid, as a weight per unit volume.
+        liquid_density: Real,
+        /// The centre of buoyancy of the rigid body, in body coordinates.
+        centre_of_buoyancy: Vec3,
+    }
+
+    impl Buoyancy {
+        /// Creates a new buoyancy force with the given parameters.
+        ///
+        /// ### Arguments
+        /// * `centre_of_buoyancy` - The point where the force is applied, in body coordinates.
+        /// * `max_depth` - The depth, from the water plane, at which the object is fully submerged.
+        /// * `volume` - The volume of the object.
+        /// * `water_height` - The height of the water plane.
+        /// * `liquid_density` - The weight per unit volume of the liquid.
+        pub fn new<T1, T2, T3, T4>(centre_of_buoyancy: Vec3, max_depth: T1, volume: T2, water_height: T3, liquid_density: T4) -> Self
+        where T1: AsReal, T2: AsReal, T3: AsReal, T4: AsReal {
+            Buoyancy {
+                centre_of_buoyancy,
+                max_depth: max_depth.as_real(),
+                volume: volume.as_real(),
+                water_height: water_height.as_real(),
+                liquid_density: liquid_density.as_real(),
+            }
+        }
+
+        /// Sets the height of the water plane.
+        pub fn set_water_height<T: AsReal>(&mut self, water_height: T) {
+            self.water_height = water_height.as_real();
+        }
+    }
+
+    impl ForceGenerator for Buoyancy {
+        /// Applies the buoyancy force to the given rigid body.
+        fn update_force(&mut self, bodies: &mut [RigidBody], index: usize, _duration: Real) {
+            let body = &mut bodies[index];
+
+            // Calculate the submersion depth
+            let point_in_world = body.get_point_in_world_space(self.centre_of_buoyancy);
+            let depth = point_in_world.y;
+
+            // Check if we're out of the water
+            if depth >= self.water_height + self.max_depth {
+                return;
+            }
+
+            let mut force = Vec3::default();
+
+            // Check if we're at maximum depth, otherwise we're partly
+            // submerged
+            if depth <= self.water_height - self.max_depth {
+                force.y = self.liquid_density * self.volume;
+            } else {
+                let submerged = (self.water_height + self.max_depth - depth) / (2.0 * self.max_depth);
+                force.y = self.liquid_density * self.volume * submerged;
+            }
+
+            body.add_force_at_body_point(force, self.centre_of_buoyancy);
+        }
+    }
+
+    /// Keeps track of one force generator and the rigid body it
+    /// applies to.
+    pub struct ForceRegistration {
+        /// The index of the body the force is applied to.
+        pub body: usize,
+        /// The generator of the force.
+        pub generator: Rc<RefCell<dyn ForceGenerator>>,
+    }
+
+    /// Holds all the force generators and the rigid bodies they apply to.
+    #[derive(Default)]
+    pub struct ForceRegistry {
+        /// Holds the list of registrations.
+        registrations: Vec<ForceRegistration>,
+    }
+
+    impl ForceRegistry {
+        /// Creates a new empty registry.
+        pub fn new() -> Self {
+            ForceRegistry { registrations: Vec::new() }
+        }
+
+        /// Registers the given force generator to apply to the rigid
+        /// body at the given index.
+        pub fn add(&mut self, body: usize, generator: Rc<RefCell<dyn ForceGenerator>>) {
+            self.registrations.push(ForceRegistration { body, generator });
+        }
+
+        /// Removes the given registered pair from the registry. If the
+        /// pair is not registered, this method will have no effect.
+        pub fn remove(&mut self, body: usize, generator: &Rc<RefCell<dyn ForceGenerator>>) {
+            self.registrations.retain(|registration| {
+                registration.body != body || !Rc::ptr_eq(&registration.generator, generator)
+            });
+        }
+
+        /// Clears all registrations from the registry. This will not
+        /// delete the bodies or the force generators themselves, just
+        /// the records of their connection.
+        pub fn clear(&mut self) {
+            self.registrations.clear();
+        }
+
+        /// Returns the number of registrations in the registry.
+        pub fn len(&self) -> usize {
+            self.registrations.len()
+        }
+
+        /// Returns true if there are no registrations in the registry.
+        pub fn is_empty(&self) -> bool {
+            self.registrations.is_empty()
+        }
+
+        /// Calls all the force generators to update the forces of
+        /// their corresponding rigid bodies.
+        pub fn update_forces<T: AsReal>(&mut self, bodies: &mut [RigidBody], duration: T) {
+            let duration = duration.as_real();
+
+            for registration in self.registrations.iter() {
+                registration.generator
+                    .borrow_mut()
+                    .update_force(bodies, registration.body, duration);
+            }
+        }
+    }
+}
+
+pub mod inertia {
+    use super::precision::*;
+    use super::core::*;
+
+    /// Creates an inertia tensor from its principal moments of inertia
+    /// and its products of inertia.
+    pub fn from_coeffs<T1, T2, T3, T4, T5, T6>(ix: T1, iy: T2, iz: T3, ixy: T4, ixz: T5, iyz: T6) -> Matrix3
+    where T1: AsReal, T2: AsReal, T3: AsReal, T4: AsReal, T5: AsReal, T6: AsReal {
+        let (ixy, ixz, iyz) = (ixy.as_real(), ixz.as_real(), iyz.as_real());
+
+        Matrix3::new([
+            ix.as_real(), -ixy, -ixz,
+            -ixy, iy.as_real(), -iyz,
+            -ixz, -iyz, iz.as_real(),
+        ])
+    }
+
+    /// Returns the inertia tensor of a solid sphere of the given mass
+    /// and radius.
+    pub fn solid_sphere<T: AsReal, U: AsReal>(mass: T, radius: U) -> Matrix3 {
+        let moment = 0.4 * mass.as_real() * radius.as_real().powi(2);
+        Matrix3::from_diagonal(moment, moment, moment)
+    }
+
+    /// Returns the inertia tensor of a hollow sphere (a thin spherical
+    /// shell) of the given mass and radius.
+    pub fn hollow_sphere<T: AsReal, U: AsReal>(mass: T, radius: U) -> Matrix3 {
+        let moment = 2.0 / 3.0 * mass.as_real() * radius.as_real().powi(2);
+        Matrix3::from_diagonal(moment, moment, moment)
+    }
+
+    /// Returns the inertia tensor of a solid box of the given mass,
+    /// with the given half-sizes along each axis.
+    pub fn solid_box<T: AsReal>(mass: T, half_sizes: Vec3) -> Matrix3 {
+        let mass = mass.as_real();
+        let squares = half_sizes.elementwise_prod(half_sizes);
+
+        Matrix3::from_diagonal(
+            mass / 3.0 * (squares.y + squares.z),
+            mass / 3.0 * (squares.x + squares.z),
+            mass / 3.0 * (squares.x + squares.y)
+        )
+    }
+
+    /// Returns the inertia tensor of a hollow box (with thin walls) of
+    /// the given mass, with the given half-sizes along each axis.
+    pub fn hollow_box<T: AsReal>(mass: T, half_sizes: Vec3) -> Matrix3 {
+        let mass = mass.as_real();
+        let (x, y, z) = (half_sizes.x, half_sizes.y, half_sizes.z);
+
+        // The mass is spread over the faces in proportion to their area
+        let area = x * y + x * z + y * z;
+
+        if area <= 0 as Real {
+            return Matrix3::zero();
+        }
+
+        // Moment around the first axis, for the half-sizes a and b along
+        // the other two axes and c along the first one
+        let moment = |a: Real, b: Real, c: Real| {
+            // Faces normal to the other two axes, and to the first one
+            let side = (a * c * (a * a / 3.0 + b * b) + b * c * (b * b / 3.0 + a * a)) / area;
+            let cap = a * b * (a * a + b * b) / 3.0 / area;
+            mass * (side + cap)
+        };
+
+        Matrix3::from_diagonal(moment(y, z, x), moment(x, z, y), moment(x, y, z))
+    }
+
+    /// Returns the inertia tensor of a solid cylinder of the given mass,
+    /// radius and height, with its axis along the y axis.
+    pub fn solid_cylinder<T, U, V>(mass: T, radius: U, height: V) -> Matrix3
+    where T: AsReal, U: AsReal, V: AsReal {
+        let (mass, radius, height) = (mass.as_real(), radius.as_real(), height.as_real());
+        let side = mass / 12.0 * (3.0 * radius * radius + height * height);
+        Matrix3::from_diagonal(side, 0.5 * mass * radius * radius, side)
+    }
+
+    /// Returns the inertia tensor of a hollow cylinder (a thin tube
+    /// without caps) of the given mass, radius and height, with its
+    /// axis along the y axis.
+    pub fn hollow_cylinder<T, U, V>(mass: T, radius: U, height: V) -> Matrix3
+    where T: AsReal, U: AsReal, V: AsReal {
+        let (mass, radius, height) = (mass.as_real(), radius.as_real(), height.as_real());
+        let side = mass / 12.0 * (6.0 * radius * radius + height * height);
+        Matrix3::from_diagonal(side, mass * radius * radius, side)
+    }
+
+    /// Returns the inertia tensor of a solid capsule of the given mass,
+    /// radius and height of its cylindrical part, with its axis along
+    /// the y axis.
+    pub fn solid_capsule<T, U, V>(mass: T, radius: U, height: V) -> Matrix3
+    where T: AsReal, U: AsReal, V: AsReal {
+        let (mass, radius, height) = (mass.as_real(), radius.as_real(), height.as_real());
+
+        // Split the mass between the cylinder and the two hemispheres,
+        // by their volumes divided by pi r^2
+        let cylinder_volume = height;
+        let sphere_volume = 4.0 / 3.0 * radius;
+        let cylinder_mass = mass * cylinder_volume / (cylinder_volume + sphere_volume);
+        let sphere_mass = mass - cylinder_mass;
+
+        let r2 = radius * radius;
+        let axial = cylinder_mass * r2 / 2.0 + sphere_mass * 0.4 * r2;
+        let side = cylinder_mass * (height * height / 12.0 + r2 / 4.0)
+            + sphere_mass * (0.4 * r2 + height * height / 4.0 + 3.0 / 8.0 * height * radius);
+
+        Matrix3::from_diagonal(side, axial, side)
+    }
+
+    /// Returns the inertia tensor of a solid cone of the given mass,
+    /// base radius and height, with its axis along the y axis. The
+    /// tensor is given around the centre of mass, which lies on the
+    /// axis at a quarter of the height from the base.
+    pub fn solid_cone<T, U, V>(mass: T, radius: U, height: V) -> Matrix3
+    where T: AsReal, U: AsReal, V: AsReal {
+        let (mass, radius, height) = (mass.as_real(), radius.as_real(), height.as_real());
+        let side = mass * (3.0 / 20.0 * radius * radius + 3.0 / 80.0 * height * height);
+        Matrix3::from_diagonal(side, 0.3 * mass * radius * radius, side)
+    }
+
+    /// Returns the inertia tensor around a point at the given offset
+    /// from the centre of mass, by the parallel axis theorem. The given
+    /// tensor must be around the centre of mass.
+    pub fn translate<T: AsReal>(tensor: Matrix3, mass: T, offset: Vec3) -> Matrix3 {
+        let mass = mass.as_real();
+        let d = offset;
+
+        tensor + Matrix3::new([
+            d.y * d.y + d.z * d.z, -d.x * d.y, -d.x * d.z,
+            -d.x * d.y, d.x * d.x + d.z * d.z, -d.y * d.z,
+            -d.x * d.z, -d.y * d.z, d.x * d.x + d.y * d.y,
+        ]) * mass
+    }
+
+    /// Holds the mass properties of a body, or one part of a
+    /// compound body.
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub struct MassProperties {
+        /// The mass of the body.
+        pub mass: Real,
+        /// The centre of mass of the body, in body coordinates.
+        pub centre_of_mass: Vec3,
+        /// The inertia tensor of the body around its centre of mass.
+        pub inertia_tensor: Matrix3,
+    }
+
+    impl MassProperties {
+        /// Creates the mass properties of a body, or one part of a
+        /// compound body.
+        pub fn new<T: AsReal>(mass: T, centre_of_mass: Vec3, inertia_tensor: Matrix3) -> Self {
+            MassProperties { mass: mass.as_real(), centre_of_mass, inertia_tensor }
+        }
+    }
+
+    /// Combines the mass properties of the parts of a compound body,
+    /// returning the total mass, the combined centre of mass and the
+    /// inertia tensor around it. The parts must have the same
+    /// orientation as the compound body.
+    pub fn combine(parts: &[MassProperties]) -> MassProperties {
+        let mass: Real = parts.iter().map(|part| part.mass).sum();
+
+        if mass <= 0 as Real {
+            return MassProperties::new(0, Vec3::default(), Matrix3::zero());
+        }
+
+        // Find the combined centre of mass
+        let mut centre_of_mass = Vec3::default();
+
+        for part in parts {
+            centre_of_mass.add_scaled_vector(part.centre_of_mass, part.mass / mass);
+        }
+
+        // Move each tensor to the combined centre of mass and add them
+        let mut inertia_tensor = Matrix3::zero();
+
+        for part in parts {
+            let offset = part.centre_of_mass - centre_of_mass;
+            inertia_tensor += translate(part.inertia_tensor, part.mass, offset);
+        }
+
+        MassProperties { mass, centre_of_mass, inertia_tensor }
+    }
+}
+
+pub mod collide_fine {
+    use super::precision::*;
+    use super::core::*;
+    use super::rigid_body::*;
+    use super::contacts::*;
+
+    /// Represents a primitive to detect collisions against, attached
+    /// to a rigid body with an offset from its origin.
+    #[derive(Debug, Copy, Clone)]
+    pub struct CollisionPrimitive {
+        /// The index of the rigid body that is represented by this
+        /// primitive, or `None` for the scenery.
+        pub body: Option<usize>,
+        /// The offset of this primitive from the given rigid body.
+        pub offset: Matrix4,
+        /// The resultant transform of the primitive. This is calculated
+        /// by combining the offset of the primitive with the transform
+        /// of the rigid body.
+        transform: Matrix4,
+    }
+
+    impl CollisionPrimitive {
+        /// Creates a primitive attached to the given body, with the given
+        /// offset transform in body space.
+        pub fn new(body: Option<usize>, offset: Matrix4) -> Self {
+            CollisionPrimitive { body, offset, transform: offset }
+        }
+
+        /// Calculates the internals for the primitive, which must be
+        /// called whenever the body has been moved.
+        pub fn calculate_internals(&mut self, bodies: &[RigidBody]) {
+            self.transform = match self.body {
+                Some(body) => bodies[body].get_transform() * self.offset,
+                None => self.offset,
+            };
+        }
+
+        /// This is a convenience function to allow access to the axis
+        /// vectors in the transform for this primitive. The fourth
+        /// axis (index three) is the position of the primitive.
+        pub fn get_axis(&self, index: usize) -> Vec3 {
+            self.transform.get_axis_vector(index)
+        }
+
+        /// Returns the position of the primitive in world space.
+        pub fn get_position(&self) -> Vec3 {
+            self.get_axis(3)
+        }
+
+        /// Returns the resultant transform of the primitive, calculated
+        /// from the combined offset of the primitive and the transform
+        /// (orientation + position) of the rigid body to which it is
+        /// attached.
+        pub fn get_transform(&self) -> Matrix4 {
+            self.transform
+        }
+    }
+
+    /// Represents a rigid body that can be treated as a sphere for
+    /// collision detection.
+    #[derive(Debug, Copy, Clone)]
+    pub struct CollisionSphere {
+        /// The primitive the sphere is built on.
+        pub primitive: CollisionPrimitive,
+        /// The radius of the sphere.
+        pub radius: Real,
+    }
+
+    impl CollisionSphere {
+        /// Creates a sphere attached to the given body, with its centre
+        /// at the given offset.
+        pub fn new<T: AsReal>(body: Option<usize>, offset: Matrix4, radius: T) -> Self {
+            CollisionSphere { primitive: CollisionPrimitive::new(body, offset), radius: radius.as_real() }
+        }
+    }
+
+    /// Represents a rigid body that can be treated as an oriented
+    /// box for collision detection.
+    #[derive(Debug, Copy, Clone)]
+    pub struct CollisionBox {
+        /// The primitive the box is built on.
+        pub primitive: CollisionPrimitive,
+        /// Holds the half-sizes of the box along each of its local axes.
+        pub half_size: Vec3,
+    }
+
+    impl CollisionBox {
+        /// Creates a box attached to the given body, with its centre at
+        /// the given offset.
+        pub fn new(body: Option<usize>, offset: Matrix4, half_size: Vec3) -> Self {
+            CollisionBox { primitive: CollisionPrimitive::new(body, offset), half_size }
+        }
+
+        /// Returns the vertices of the box in world space.
+        pub fn get_vertices(&self) -> [Vec3; 8] {
+            let h = self.half_size;
+            let transform = self.primitive.get_transform();
+
+            [
+                transform * Vec3::new(-h.x, -h.y, -h.z),
+                transform * Vec3::new(-h.x, -h.y, h.z),
+                transform * Vec3::new(-h.x, h.y, -h.z),
+                transform * Vec3::new(-h.x, h.y, h.z),
+                transform * Vec3::new(h.x, -h.y, -h.z),
+                transform * Vec3::new(h.x, -h.y, h.z),
+                transform * Vec3::new(h.x, h.y, -h.z),
+                transform * Vec3::new(h.x, h.y, h.z),
+            ]
+        }
+    }
+
+    /// The plane is not a primitive: it doesn't represent another rigid
+    /// body. It is used for contacts with the immovable world geometry,
+    /// as a half-space where everything behind the plane is solid.
+    #[derive(Debug, Copy, Clone)]
+    pub struct CollisionPlane {
+        /// The plane normal.
+        pub direction: Vec3,
+        /// The distance of the plane from the origin.
+        pub offset: Real,
+    }
+
+    impl CollisionPlane {
+        /// Creates a plane with the given normal and distance from the
+        /// origin. The normal is normalised before being stored.
+        pub fn new<T: AsReal>(direction: Vec3, offset: T) -> Self {
+            let mut direction = direction;
+            direction.normalize();
+            CollisionPlane { direction, offset: offset.as_real() }
+        }
+    }
+
+    /// Represents a rigid body that can be treated as a capsule for
+    /// collision detection: a segment along the local y axis swept by
+    /// a sphere.
+    #[derive(Debug, Copy, Clone)]
+    pub struct CollisionCapsule {
+        /// The primitive the capsule is built on.
+        pub primitive: CollisionPrimitive,
+        /// The radius of the capsule.
+        pub radius: Real,
+        /// Half the length of the inner segment of the capsule.
+        pub half_height: Real,
+    }
+
+    impl CollisionCapsule {
+        /// Creates a capsule attached to the given body, with its centre
+        /// at the given offset.
+        pub fn new<T: AsReal, U: AsReal>(body: Option<usize>, offset: Matrix4, radius: T, half_height: U) -> Self {
+            CollisionCapsule {
+                primitive: CollisionPrimitive::new(body, offset),
+                radius: radius.as_real(),
+                half_height: half_height.as_real(),
+            }
+        }
+
+        /// Returns the two end points of the inner segment of the
+        /// capsule in world space.
+        pub fn get_segment(&self) -> (Vec3, Vec3) {
+            let transform = self.primitive.get_transform();
+
+            (
+                transform * Vec3::new(0, -self.half_height, 0),
+                transform * Vec3::new(0, self.half_height, 0),
+            )
+        }
+    }
+
+    /// A helper structure that contains information for the detector
+    /// to use in building its contact data.
+    #[derive(Debug, Clone)]
+    pub struct CollisionData {
+        /// Holds the contacts generated so far.
+        contacts: Vec<Contact>,
+        /// Holds the maximum number of contacts the buffer can take.
+        max_contacts: usize,
+        /// Holds the friction value to write into any collisions.
+        pub friction: Real,
+        /// Holds the restitution value to write into any collisions.
+        pub restitution: Real,
+    }
+
+    impl CollisionData {
+        /// Creates an empty buffer that can take up to the given
+        /// number of contacts.
+        pub fn new(max_contacts: usize) -> Self {
+            CollisionData {
+                contacts: Vec::with_capacity(max_contacts),
+                max_contacts,
+                friction: 0 as Real,
+                restitution: 0 as Real,
+            }
+        }
+
+        /// Checks if there are more contacts available in the contact data.
+        pub fn has_more_contacts(&self) -> bool {
+            self.contacts.len() < self.max_contacts
+        }
+
+        /// Returns the number of contacts that can still be added.
+        pub fn contacts_left(&self) -> usize {
+            self.max_contacts - self.contacts.len()
+        }
+
+        /// Resets the data so that it has no used contacts recorded.
+        pub fn reset(&mut self) {
+            self.contacts.clear();
+        }
+
+        /// Returns the contacts generated so far.
+        pub fn get_contacts(&self) -> &[Contact] {
+            &self.contacts
+        }
+
+        /// Returns the contacts generated so far, mutably.
+        pub fn get_contacts_mut(&mut self) -> &mut [Contact] {
+            &mut self.contacts
+        }
+
+        /// Adds a contact with the friction and restitution of the data,
+        /// returning false if there is no room left for it.
+        pub fn add_contact(&mut self, bodies: [Option<usize>; 2], contact_point: Vec3, contact_normal: Vec3, penetration: Real) -> bool {
+            if !self.has_more_contacts() {
+                return false;
+            }
+
+            self.contacts.push(Contact::new(
+                bodies, contact_point, contact_normal, penetration, self.friction, self.restitution
+            ));
+
+            true
+        }
+    }
+
+    /// Returns the half-length of the box when projected onto the
+    /// given axis.
+    fn transform_to_axis(cube: &CollisionBox, axis: Vec3) -> Real {
+        cube.half_size.x * axis.dot(cube.primitive.get_axis(0)).abs() +
+        cube.half_size.y * axis.dot(cube.primitive.get_axis(1)).abs() +
+        cube.half_size.z * axis.dot(cube.primitive.get_axis(2)).abs()
+    }
+
+    /// Checks if the two boxes overlap along the given axis, returning
+    /// the amount of overlap. The final parameter `to_centre` is used to
+    /// pass in the vector between the boxes centre points, to avoid
+    /// having to recalculate it each time.
+    fn penetration_on_axis(one: &CollisionBox, two: &CollisionBox, axis: Vec3, to_centre: Vec3) -> Real {
+        // Project the half-size of one onto axis
+        let one_project = transform_to_axis(one, axis);
+        let two_project = transform_to_axis(two, axis);
+
+        // Project this onto the axis
+        let distance = to_centre.dot(axis).abs();
+
+        // Return the overlap (i.e. positive indicates
+        // overlap, negative indicates separation).
+        one_project + two_project - distance
+    }
+
+    /// Returns the point of the segment closest to the given point.
+    fn closest_point_on_segment(start: Vec3, end: Vec3, point: Vec3) -> Vec3 {
+        let segment = end - start;
+        let length = segment.square_magnitude();
+
+        if length <= 0 as Real {
+            return start;
+        }
+
+        let t = ((point - start).dot(segment) / length).clamp(0.0, 1.0);
+        start + segment * t
+    }
+
+    /// Returns the closest points between the two segments, given by
+    /// their start and end points.
+    fn closest_points_between_segments(p1: Vec3, q1: Vec3, p2: Vec3, q2: Vec3) -> (Vec3, Vec3) {
+        let d1 = q1 - p1;
+        let d2 = q2 - p2;
+        let r = p1 - p2;
+        let a = d1.square_magnitude();
+        let e = d2.square_magnitude();
+        let f = d2.dot(r);
+
+        // Check if either or both segments degenerate into points
+        if a <= REAL_EPSILON && e <= REAL_EPSILON {
+            return (p1, p2);
+        }
+
+        let (s, t) = if a <= REAL_EPSILON {
+            (0 as Real, (f / e).clamp(0.0, 1.0))
+        } else {
+            let c = d1.dot(r);
+
+            if e <= REAL_EPSILON {
+                ((-c / a).clamp(0.0, 1.0), 0 as Real)
+            } else {
+                let b = d1.dot(d2);
+                let denom = a * e - b * b;
+
+                // If the segments are parallel pick any s
+                let mut s = if denom != 0 as Real {
+                    ((b * f - c * e) / denom).clamp(0.0, 1.0)
+                } else {
+                    0 as Real
+                };
+
+                let mut t = (b * s + f) / e;
+
+                if t < 0 as Real {
+                    t = 0 as Real;
+                    s = (-c / a).clamp(0.0, 1.0);
+                } else if t > 1 as Real {
+                    t = 1 as Real;
+                    s = ((b - c) / a).clamp(0.0, 1.0);
+                }
+
+                (s, t)
+            }
+        };
+
+        (p1 + d1 * s, p2 + d2 * t)
+    }
+
+    /// Returns the point where two edges of boxes come closest. If the
+    /// closest points fall outside the edges, the middle of the edge of
+    /// one box (given by `use_one`) is used instead, as we have an
+    /// edge-face contact.
+    fn edge_contact_point(
+        p_one: Vec3, d_one: Vec3, one_size: Real,
+        p_two: Vec3, d_two: Vec3, two_size: Real,
+        use_one: bool
+    ) -> Vec3 {
+        let sm_one = d_one.square_magnitude();
+        let sm_two = d_two.square_magnitude();
+        let dp_one_two = d_two.dot(d_one);
+
+        let to_st = p_one - p_two;
+        let dp_sta_one = d_one.dot(to_st);
+        let dp_sta_two = d_two.dot(to_st);
+
+        let denom = sm_one * sm_two - dp_one_two * dp_one_two;
+
+        // Zero denominator indicates parrallel lines
+        if denom.abs() < 0.0001 {
+            return if use_one { p_one } else { p_two };
+        }
+
+        let mua = (dp_one_two * dp_sta_two - sm_two * dp_sta_one) / denom;
+        let mub = (sm_one * dp_sta_two - dp_one_two * dp_sta_one) / denom;
+
+        // If either of the edges has the nearest point out of bounds,
+        // then the edges aren't crossed, we have an edge-face contact.
+        // Our point is on the edge, which we know from the use_one
+        // parameter.
+        if mua > one_size || mua < -one_size || mub > two_size || mub < -two_size {
+            if use_one { p_one } else { p_two }
+        } else {
+            let c_one = p_one + d_one * mua;
+            let c_two = p_two + d_two * mub;
+            c_one * 0.5 + c_two * 0.5
+        }
+    }
+
+    /// A wrapper class that holds fast intersection tests. These can be
+    /// used to drive the coarse collision detection system or as an
+    /// early out in the full collision tests.
+    pub struct IntersectionTests;
+
+    impl IntersectionTests {
+        /// Returns true if the sphere intersects the half-space.
+        pub fn sphere_and_half_space(sphere: &CollisionSphere, plane: &CollisionPlane) -> bool {
+            // Find the distance from the origin
+            let ball_distance = plane.direction.dot(sphere.primitive.get_position()) - sphere.radius;
+
+            // Check for the intersection
+            ball_distance <= plane.offset
+        }
+
+        /// Returns true if the two spheres intersect.
+        pub fn sphere_and_sphere(one: &CollisionSphere, two: &CollisionSphere) -> bool {
+            // Find the vector between the objects
+            let midline = one.primitive.get_position() - two.primitive.get_position();
+
+            // See if it is large enough.
+            midline.square_magnitude() < (one.radius + two.radius).powi(2)
+        }
+
+        /// Returns true if the box intersects the half-space.
+        pub fn box_and_half_space(cube: &CollisionBox, plane: &CollisionPlane) -> bool {
+            // Work out the projected radius of the box onto the plane direction
+            let projected_radius = transform_to_axis(cube, plane.direction);
+
+            // Work out how far the box is from the origin
+            let box_distance = plane.direction.dot(cube.primitive.get_position()) - projected_radius;
+
+            // Check for the intersection
+            box_distance <= plane.offset
+        }
+
+        /// Returns true if the two boxes intersect, using the separating
+        /// axis test.
+        pub fn box_and_box(one: &CollisionBox, two: &CollisionBox) -> bool {
+            // Find the vector between the two centres
+            let to_centre = two.primitive.get_position() - one.primitive.get_position();
+
+            box_and_box_axes(one, two).iter().all(|axis| {
+                axis.square_magnitude() < 0.001 ||
+                penetration_on_axis(one, two, *axis, to_centre) >= 0 as Real
+            })
+        }
+    }
+
+    /// Returns the fifteen axes to test for the separation of two boxes:
+    /// the face axes of both boxes and the cross products of their edges.
+    fn box_and_box_axes(one: &CollisionBox, two: &CollisionBox) -> [Vec3; 15] {
+        let mut axes = [Vec3::default(); 15];
+
+        for i in 0..3 {
+            axes[i] = one.primitive.get_axis(i);
+            axes[i + 3] = two.primitive.get_axis(i);
+        }
+
+        for i in 0..3 {
+            for j in 0..3 {
+                axes[6 + i * 3 + j] = axes[i].vec_prod(axes[j + 3]);
+            }
+        }
+
+        axes
+    }
+
+    /// A wrapper class that holds the fine grained collision detection
+    /// routines.
+    ///
+    /// Each of the functions has the same format: it takes the details
+    /// of two objects, and a collision data structure to fill. Each
+    /// function returns the number of contacts it wrote into the
+    /// collision data.
+    pub struct CollisionDetector;
+
+    impl CollisionDetector {
+        /// Generates the contact of a sphere with a half-space.
+        pub fn sphere_and_half_space(sphere: &CollisionSphere, plane: &CollisionPlane, data: &mut CollisionData) -> usize {
+            sphere_and_half_space_at(
+                sphere.primitive.get_position(), sphere.radius, sphere.primitive.body, plane, data
+            )
+        }
+
+        /// Generates the contact of a sphere with a plane, which can be
+        /// crossed from either side.
+        pub fn sphere_and_true_plane(sphere: &CollisionSphere, plane: &CollisionPlane, data: &mut CollisionData) -> usize {
+            // Make sure we have contacts
+            if !data.has_more_contacts() {
+                return 0;
+            }
+
+            // Cache the sphere position
+            let position = sphere.primitive.get_position();
+
+            // Find the distance from the plane
+            let centre_distance = plane.direction.dot(position) - plane.offset;
+
+            // Check if we're within radius
+            if centre_distance * centre_distance > sphere.radius * sphere.radius {
+                return 0;
+            }
+
+            // Check which side of the plane we're on
+            let (normal, penetration) = if centre_distance < 0 as Real {
+                (-plane.direction, centre_distance)
+            } else {
+                (plane.direction, -centre_distance)
+            };
+
+            let point = position - plane.direction * centre_distance;
+            data.add_contact([sphere.primitive.body, None], point, normal, penetration + sphere.radius);
+            1
+        }
+
+        /// Generates the contact of two spheres.
+        pub fn sphere_and_sphere(one: &CollisionSphere, two: &CollisionSphere, data: &mut CollisionData) -> usize {
+            sphere_and_sphere_at(
+                one.primitive.get_position(), one.radius, one.primitive.body,
+                two.primitive.get_position(), two.radius, two.primitive.body,
+                data
+            )
+        }
+
+        /// Generates the contacts of a box with a half-space, one for
+        /// each vertex of the box behind the plane.
+        pub fn box_and_half_space(cube: &CollisionBox, plane: &CollisionPlane, data: &mut CollisionData) -> usize {
+            // Make sure we have contacts
+            if !data.has_more_contacts() {
+                return 0;
+            }
+
+            // Check for intersection
+            if !IntersectionTests::box_and_half_space(cube, plane) {
+                return 0;
+            }
+
+            // We have an intersection, so find the intersection points.
+            // We can make do with only checking vertices.
+            let mut contacts_used = 0;
+
+            for vertex in cube.get_vertices() {
+                // Calculate the distance from the plane
+                let vertex_distance = vertex.dot(plane.direction);
+
+                // Compare this to the plane's distance
+                if vertex_distance <= plane.offset {
+                    // The contact point is halfway between the vertex
+                    // and the plane
+                    let penetration = plane.offset - vertex_distance;
+                    let point = vertex + plane.direction * (penetration * 0.5);
+
+                    if !data.add_contact([cube.primitive.body, None], point, plane.direction, penetration) {
+                        break;
+                    }
+
+                    contacts_used += 1;
+                }
+            }
+
+            contacts_used
+        }
+
+        /// Generates the contact of a box and a sphere.
+        pub fn box_and_sphere(cube: &CollisionBox, sphere: &CollisionSphere, data: &mut CollisionData) -> usize {
+            box_and_sphere_at(cube, sphere.primitive.get_position(), sphere.radius, sphere.primitive.body, data)
+        }
+
+        /// Generates the contact of a box and a point, given in world
+        /// space, which belongs to the scenery.
+        pub fn box_and_point(cube: &CollisionBox, point: Vec3, data: &mut CollisionData) -> usize {
+            // Make sure we have contacts
+            if !data.has_more_contacts() {
+                return 0;
+            }
+
+            // Transform the point into box coordinates
+            let rel_pt = cube.primitive.get_transform().transform_inverse(point);
+            let rel = [rel_pt.x, rel_pt.y, rel_pt.z];
+            let half = [cube.half_size.x, cube.half_size.y, cube.half_size.z];
+
+            // Check each axis, looking for the axis on which the
+            // penetration is least deep.
+            let mut min_depth = REAL_MAX;
+            let mut normal = Vec3::default();
+
+            for i in 0..3 {
+                let depth = half[i] - rel[i].abs();
+
+                if depth < 0 as Real {
+                    return 0;
+                }
+
+                if depth < min_depth {
+                    min_depth = depth;
+                    normal = cube.primitive.get_axis(i) * if rel[i] < 0 as Real { 1 } else { -1 };
+                }
+            }
+
+            data.add_contact([cube.primitive.body, None], point, normal, min_depth);
+            1
+        }
+
+        /// Generates the contact of two boxes, using the separating axis
+        /// test to find either a vertex-face or an edge-edge contact.
+        pub fn box_and_box(one: &CollisionBox, two: &CollisionBox, data: &mut CollisionData) -> usize {
+            // Make sure we have contacts
+            if !data.has_more_contacts() {
+                return 0;
+            }
+
+            // Find the vector between the two centres
+            let to_centre = two.primitive.get_position() - one.primitive.get_position();
+
+            // We start assuming there is no contact
+            let mut pen = REAL_MAX;
+            let mut best = usize::MAX;
+            let mut best_single_axis = usize::MAX;
+
+            // Now we check each axes, returning if it gives us
+            // a separating axis, and keeping track of the axis with
+            // the smallest penetration otherwise.
+            for (index, axis) in box_and_box_axes(one, two).iter().enumerate() {
+                // Store the best axis-major, in case we run into almost
+                // parallel edge collisions later
+                if index == 6 {
+                    best_single_axis = best;
+                }
+
+                // Check for axes that were generated by (almost) parallel edges.
+                if axis.square_magnitude() < 0.001 {
+                    continue;
+                }
+
+                let mut axis = *axis;
+                axis.normalize();
+
+                let overlap = penetration_on_axis(one, two, axis, to_centre);
+
+                if overlap < 0 as Real {
+                    return 0;
+                }
+
+                if overlap < pen {
+                    pen = overlap;
+                    best = index;
+                }
+            }
+
+            // Make sure we've got a result.
+            if best == usize::MAX {
+                return 0;
+            }
+
+            if best < 3 {
+                // We've got a vertex of box two on a face of box one.
+                fill_point_face_box_box(one, two, to_centre, data, best, pen);
+                1
+            } else if best < 6 {
+                // We've got a vertex of box one on a face of box two.
+                // We use the same algorithm as above, but swap around
+                // one and two (and therefore also the vector between their
+                // centres).
+                fill_point_face_box_box(two, one, -to_centre, data, best - 3, pen);
+                1
+            } else {
+                // We've got an edge-edge contact. Find out which axes
+                let best = best - 6;
+                let one_axis_index = best / 3;
+                let two_axis_index = best % 3;
+                let one_axis = one.primitive.get_axis(one_axis_index);
+                let two_axis = two.primitive.get_axis(two_axis_index);
+                let mut axis = one_axis.vec_prod(two_axis);
+                axis.normalize();
+
+                // The axis should point from box one to box two.
+                if axis.dot(to_centre) > 0 as Real {
+                    axis = -axis;
+                }
+
+                // We have the axes, but not the edges: each axis has 4 edges
+                // parallel to it, we need to find which of the 4 for each
+                // object. We do that by finding the point in the centre of
+                // the edge. We know its component in the direction of the
+                // box's collision axis is zero (its a mid-point) and we
+                // determine which of the extremes in each of the other axes
+                // is closest.
+                let one_half = [one.half_size.x, one.half_size.y, one.half_size.z];
+                let two_half = [two.half_size.x, two.half_size.y, two.half_size.z];
+                let mut pt_on_one_edge = one_half;
+                let mut pt_on_two_edge = two_half;
+
+                for i in 0..3 {
+                    if i == one_axis_index {
+                        pt_on_one_edge[i] = 0 as Real;
+                    } else if one.primitive.get_axis(i).dot(axis) > 0 as Real {
+                        pt_on_one_edge[i] = -pt_on_one_edge[i];
+                    }
+
+                    if i == two_axis_index {
+                        pt_on_two_edge[i] = 0 as Real;
+                    } else if two.primitive.get_axis(i).dot(axis) < 0 as Real {
+                        pt_on_two_edge[i] = -pt_on_two_edge[i];
+                    }
+                }
+
+                // Move them into world coordinates (they are already oriented
+                // correctly, since they have been derived from the axes).
+                let pt_on_one_edge = one.primitive.get_transform()
+                    * Vec3::new(pt_on_one_edge[0], pt_on_one_edge[1], pt_on_one_edge[2]);
+                let pt_on_two_edge = two.primitive.get_transform()
+                    * Vec3::new(pt_on_two_edge[0], pt_on_two_edge[1], pt_on_two_edge[2]);
+
+                // So we have a point and a direction for the colliding edges.
+                // We need to find out point of closest approach of the two
+                // line-segments.
+                let vertex = edge_contact_point(
+                    pt_on_one_edge, one_axis, one_half[one_axis_index],
+                    pt_on_two_edge, two_axis, two_half[two_axis_index],
+                    best_single_axis > 2
+                );
+
+                // We can fill the contact.
+                data.add_contact([one.primitive.body, two.primitive.body], vertex, axis, pen);
+                1
+            }
+        }
+
+        /// Generates the contacts of a capsule with a half-space, one for
+        /// each end of the capsule behind the plane.
+        pub fn capsule_and_half_space(capsule: &CollisionCapsule, plane: &CollisionPlane, data: &mut CollisionData) -> usize {
+            let (start, end) = capsule.get_segment();
+            let body = capsule.primitive.body;
+
+            sphere_and_half_space_at(start, capsule.radius, body, plane, data)
+                + sphere_and_half_space_at(end, capsule.radius, body, plane, data)
+        }
+
+        /// Generates the contact of a capsule and a sphere.
+        pub fn capsule_and_sphere(capsule: &CollisionCapsule, sphere: &CollisionSphere, data: &mut CollisionData) -> usize {
+            let (start, end) = capsule.get_segment();
+            let centre = sphere.primitive.get_position();
+            let closest = closest_point_on_segment(start, end, centre);
+
+            sphere_and_sphere_at(
+                closest, capsule.radius, capsule.primitive.body,
+                centre, sphere.radius, sphere.primitive.body,
+                data
+            )
+        }
+
+        /// Generates the contact of two capsules, at the closest points
+        /// of their inner segments.
+        pub fn capsule_and_capsule(one: &CollisionCapsule, two: &CollisionCapsule, data: &mut CollisionData) -> usize {
+            let (one_start, one_end) = one.get_segment();
+            let (two_start, two_end) = two.get_segment();
+            let (one_point, two_point) = closest_points_between_segments(one_start, one_end, two_start, two_end);
+
+            sphere_and_sphere_at(
+                one_point, one.radius, one.primitive.body,
+                two_point, two.radius, two.primitive.body,
+                data
+            )
+        }
+
+        /// Generates the contacts of a capsule and a box, treating the
+        /// capsule as spheres at both ends of its segment and at the
+        /// point of the segment closest to the box.
+        pub fn capsule_and_box(capsule: &CollisionCapsule, cube: &CollisionBox, data: &mut CollisionData) -> usize {
+            let (start, end) = capsule.get_segment();
+            let body = capsule.primitive.body;
+            let transform = cube.primitive.get_transform();
+
+            // Alternate between the closest points of the segment and the
+            // box a few times, to find the inner point of the segment
+            let mut closest = closest_point_on_segment(start, end, cube.primitive.get_position());
+
+            for _ in 0..2 {
+                let local = transform.transform_inverse(closest);
+                let clamped = Vec3::new(
+                    local.x.clamp(-cube.half_size.x, cube.half_size.x),
+                    local.y.clamp(-cube.half_size.y, cube.half_size.y),
+                    local.z.clamp(-cube.half_size.z, cube.half_size.z)
+                );
+                closest = closest_point_on_segment(start, end, transform * clamped);
+            }
+
+            // Box contacts are generated from the point of view of the box
+            let mut contacts_used = 0;
+            let inner_limit = (capsule.radius * 0.5).powi(2);
+
+            for (point, inner) in [(start, false), (end, false), (closest, true)] {
+                // Skip the inner point when it is already covered by an end
+                if inner
+                    && ((closest - start).square_magnitude() < inner_limit
+                    || (closest - end).square_magnitude() < inner_limit) {
+                    continue;
+                }
+
+                contacts_used += box_and_sphere_at(cube, point, capsule.radius, body, data);
+            }
+
+            contacts_used
+        }
+    }
+
+    /// Generates the contact of a sphere, given by its centre, radius
+    /// and body, with a half-space.
+    fn sphere_and_half_space_at(position: Vec3, radius: Real, body: Option<usize>, plane: &CollisionPlane, data: &mut CollisionData) -> usize {
+        // Make sure we have contacts
+        if !data.has_more_contacts() {
+            return 0;
+        }
+
+        // Find the distance from the plane
+        let ball_distance = plane.direction.dot(position) - radius - plane.offset;
+
+        if ball_distance >= 0 as Real {
+            return 0;
+        }
+
+        // Create the contact - it has a normal in the plane direction.
+        let point = position - plane.direction * (ball_distance + radius);
+        data.add_contact([body, None], point, plane.direction, -ball_distance);
+        1
+    }
+
+    /// Generates the contact of two spheres, given by their centres,
+    /// radii and bodies.
+    fn sphere_and_sphere_at(
+        one: Vec3, one_radius: Real, one_body: Option<usize>,
+        two: Vec3, two_radius: Real, two_body: Option<usize>,
+        data: &mut CollisionData
+    ) -> usize {
+        // Make sure we have contacts
+        if !data.has_more_contacts() {
+            return 0;
+        }
+
+        // Find the vector between the objects
+        let midline = one - two;
+        let size = midline.magnitude();
+
+        // See if it is large enough.
+        if size <= 0 as Real || size >= one_radius + two_radius {
+            return 0;
+        }
+
+        // We manually create the normal, because we have the
+        // size to hand.
+        let normal = midline * (1.0 / size);
+        let point = one - midline * 0.5;
+        data.add_contact([one_body, two_body], point, normal, one_radius + two_radius - size);
+        1
+    }
+
+    /// Generates the contact of a box and a sphere, given by its centre,
+    /// radius and body.
+    fn box_and_sphere_at(cube: &CollisionBox, centre: Vec3, radius: Real, body: Option<usize>, data: &mut CollisionData) -> usize {
+        // Make sure we have contacts
+        if !data.has_more_contacts() {
+            return 0;
+        }
+
+        // Transform the centre of the sphere into box coordinates
+        let transform = cube.primitive.get_transform();
+        let rel_centre = transform.transform_inverse(centre);
+        let half = cube.half_size;
+
+        // Early out check to see if we can exclude the contact
+        if rel_centre.x.abs() - radius > half.x ||
+           rel_centre.y.abs() - radius > half.y ||
+           rel_centre.z.abs() - radius > half.z {
+            return 0;
+        }
+
+        // Clamp each coordinate to the box.
+        let closest_pt = Vec3::new(
+            rel_centre.x.clamp(-half.x, half.x),
+            rel_centre.y.clamp(-half.y, half.y),
+            rel_centre.z.clamp(-half.z, half.z)
+        );
+
+        // Check we're in contact
+        let dist = (closest_pt - rel_centre).square_magnitude();
+
+        if dist > radius * radius {
+            return 0;
+        }
+
+        if dist > 0 as Real {
+            // Compile the contact
+            let closest_pt_world = transform * closest_pt;
+            let mut normal = closest_pt_world - centre;
+            normal.normalize();
+
+            data.add_contact([cube.primitive.body, body], closest_pt_world, normal, radius - dist.sqrt());
+            return 1;
+        }
+
+        // The centre is inside the box, so push it out of the face it
+        // is closest to
+        let rel = [rel_centre.x, rel_centre.y, rel_centre.z];
+        let half = [half.x, half.y, half.z];
+        let mut min_depth = REAL_MAX;
+        let mut normal = Vec3::default();
+
+        for i in 0..3 {
+            let depth = half[i] - rel[i].abs();
+
+            if depth < min_depth {
+                min_depth = depth;
+                normal = cube.primitive.get_axis(i) * if rel[i] < 0 as Real { 1 } else { -1 };
+            }
+        }
+
+        data.add_contact([cube.primitive.body, body], centre, normal, radius + min_depth);
+        1
+    }
+
+    /// This method is called when we know that a vertex from box two is
+    /// in contact with box one.
+    fn fill_point_face_box_box(
+        one: &CollisionBox, two: &CollisionBox, to_centre: Vec3,
+        data: &mut CollisionData, best: usize, pen: Real
+    ) {
+        // We know which axis the collision is on (i.e. best), but we
+        // need to work out which of the two faces on this axis.
+        let mut normal = one.primitive.get_axis(best);
+
+        if normal.dot(to_centre) > 0 as Real {
+            normal = -normal;
+        }
+
+        // Work out which vertex of box two we're colliding with.
+        let mut vertex = two.half_size;
+
+        if two.primitive.get_axis(0).dot(normal) < 0 as Real { vertex.x = -vertex.x; }
+        if two.primitive.get_axis(1).dot(normal) < 0 as Real { vertex.y = -vertex.y; }
+        if two.primitive.get_axis(2).dot(normal) < 0 as Real { vertex.z = -vertex.z; }
+
+        // Create the contact data
+        let point = two.primitive.get_transform() * vertex;
+        data.add_contact([one.primitive.body, two.primitive.body], point, normal, pen);
+    }
+}
+
+pub mod contacts {
+    use super::precision::*;
+    use super::core::*;
+    use super::rigid_body::*;
+
+    /// The closing velocity below which contacts are resolved without
+    /// bouncing, to avoid vibration of resting contacts.
+    const VELOCITY_LIMIT: Real = 0.25;
+
+    /// The fraction of the distance from the centre of mass to the
+    /// contact point a body may be rotated by when resolving
+    /// penetration, to avoid objects being flipped over.
+    const ANGULAR_LIMIT: Real = 0.2;
+
+    /// A contact represents two bodies in contact. Resolving a contact
+    /// removes their interpenetration, and applies sufficient impulse to
+    /// keep them apart. Colliding bodies may also rebound. Contacts can
+    /// be used to represent positional joints, by making the contact
+    /// constraint keep the bodies in their correct orientation.
+    ///
+    /// The contact normal points from the second body to the first, so
+    /// that it is the direction the first body must move to separate.
+    #[derive(Debug, Clone)]
+    pub struct Contact {
+        /// Holds the indices of the rigid bodies that are involved in the
+        /// contact. The second of these can be `None`, for contacts with
+        /// the scenery.
+        pub bodies: [Option<usize>; 2],
+        /// Holds the lateral friction coefficient at the contact.
+        pub friction: Real,
+        /// Holds the normal restitution coefficient at the contact.
+        pub restitution: Real,
+        /// Holds the position of the contact in world coordinates.
+        pub contact_point: Vec3,
+        /// Holds the direction of the contact in world coordinates.
+        pub contact_normal: Vec3,
+        /// Holds the depth of penetration at the contact point. If both
+        /// bodies are specified then the contact point should be midway
+        /// between the inter-penetrating points.
+        pub penetration: Real,
+        /// A transform matrix that converts co-ordinates in the contact's
+        /// frame of reference to world co-ordinates. The columns of this
+        /// matrix form an orthonormal set of vectors.
+        contact_to_world: Matrix3,
+        /// Holds the closing velocity at the point of contact. This is set
+        /// when the calculate_internals function is run.
+        contact_velocity: Vec3,
+        /// Holds the required change in velocity for this contact to be
+        /// resolved.
+        desired_delta_velocity: Real,
+        /// Holds the world space position of the contact point relative to
+        /// centre of each body. This is set when the calculate_internals
+        /// function is run.
+        relative_contact_position: [Vec3; 2],
+    }
+
+    impl Contact {
+        /// Creates a new contact between the given bodies.
+        pub fn new<T, U, V>(bodies: [Option<usize>; 2], contact_point: Vec3, contact_normal: Vec3, penetration: T, friction: U, restitution: V) -> Self
+        where T: AsReal, U: AsReal, V: AsReal {
+            Contact {
+                bodies,
+                friction: friction.as_real(),
+                restitution: restitution.as_real(),
+                contact_point,
+                contact_normal,
+                penetration: penetration.as_real(),
+                contact_to_world: Matrix3::default(),
+                contact_velocity: Vec3::default(),
+                desired_delta_velocity: 0 as Real,
+                relative_contact_position: [Vec3::default(); 2],
+            }
+        }
+
+        /// Returns the closing velocity at the point of contact, in
+        /// contact co-ordinates, as of the last time it was calculated.
+        pub fn get_contact_velocity(&self) -> Vec3 {
+            self.contact_velocity
+        }
+
+        /// Returns the change in velocity needed for this contact to be
+        /// resolved, as of the last time it was calculated.
+        pub fn get_desired_delta_velocity(&self) -> Real {
+            self.desired_delta_velocity
+        }
+
+        /// Calculates internal data from state data. This is called before
+        /// the resolution algorithm tries to do any resolution. It should
+        /// never need to be called manually.
+        fn calculate_internals(&mut self, bodies: &[RigidBody], duration: Real) {
+            // Check if the first object is None, and swap if it is.
+            if self.bodies[0].is_none() {
+                self.swap_bodies();
+            }
+
+            // Calculate an set of axis at the contact point.
+            self.calculate_contact_basis();
+
+            // Store the relative position of the contact relative to each body
+            for (i, body) in self.bodies.iter().enumerate() {
+                if let Some(body) = body {
+                    self.relative_contact_position[i] = self.contact_point - bodies[*body].get_position();
+                }
+            }
+
+            // Find the relative velocity of the bodies at the contact point.
+            self.contact_velocity = self.calculate_local_velocity(0, bodies, duration);
+
+            if self.bodies[1].is_some() {
+                self.contact_velocity -= self.calculate_local_velocity(1, bodies, duration);
+            }
+
+            // Calculate the desired change in velocity for resolution
+            self.calculate_desired_delta_velocity(bodies, duration);
+        }
+
+        /// Reverses the contact. This involves swapping the two rigid bodies
+        /// and reversing the contact normal. The internal values should then
+        /// be recalculated using calculate_internals (this is not done
+        /// automatically).
+        fn swap_bodies(&mut self) {
+            self.contact_normal.invert();
+            self.bodies.swap(0, 1);
+        }
+
+        /// Calculates an orthonormal basis for the contact point, based on
+        /// the primary friction direction (for anisotropic friction) or a
+        /// random orientation (for isotropic friction).
+        fn calculate_contact_basis(&mut self) {
+            let normal = self.contact_normal;
+
+            // Check whether the Z-axis is nearer to the X or Y axis
+            let (tangent_y, tangent_z) = if normal.x.abs() > normal.y.abs() {
+                // Scaling factor to ensure the results are normalised
+                let s = 1.0 / (normal.z * normal.z + normal.x * normal.x).sqrt();
+
+                // The new X-axis is at right angles to the world Y-axis
+                let tangent_y = Vec3::new(normal.z * s, 0, -normal.x * s);
+
+                // The new Y-axis is at right angles to the new X- and Z- axes
+                let tangent_z = Vec3::new(
+                    normal.y * tangent_y.x,
+                    normal.z * tangent_y.x - normal.x * tangent_y.z,
+                    -normal.y * tangent_y.x
+                );
+
+                (tangent_y, tangent_z)
+            } else {
+                // Scaling factor to ensure the results are normalised
+                let s = 1.0 / (normal.z * normal.z + normal.y * normal.y).sqrt();
+
+                // The new X-axis is at right angles to the world X-axis
+                let tangent_y = Vec3::new(0, -normal.z * s, normal.y * s);
+
+                // The new Y-axis is at right angles to the new X- and Z- axes
+                let tangent_z = Vec3::new(
+                    normal.y * tangent_y.z - normal.z * tangent_y.y,
+                    -normal.x * tangent_y.z,
+                    normal.x * tangent_y.y
+                );
+
+                (tangent_y, tangent_z)
+            };
+
+            // Make a matrix from the three vectors.
+            self.contact_to_world = Matrix3::from_components(normal, tangent_y, tangent_z);
+        }
+
+        /// Calculates and returns the velocity of the contact point on the
+        /// given body, in contact co-ordinates.
+        fn calculate_local_velocity(&self, body_index: usize, bodies: &[RigidBody], duration: Real) -> Vec3 {
+            let body = &bodies[self.bodies[body_index].unwrap()];
+
+            // Work out the velocity of the contact point.
+            let velocity = body.get_rotation().vec_prod(self.relative_contact_position[body_index])
+                + body.get_velocity();
+
+            // Turn the velocity into contact-coordinates.
+            let mut contact_velocity = self.contact_to_world.transform_transpose(velocity);
+
+            // Calculate the ammount of velocity that is due to forces without
+            // reactions.
+            let mut acc_velocity = self.contact_to_world
+                .transform_transpose(body.get_last_frame_acceleration() * duration);
+
+            // We ignore any component of acceleration in the contact normal
+            // direction, we are only interested in planar acceleration
+            acc_velocity.x = 0 as Real;
+
+            // Add the planar velocities - if there's enough friction they will
+            // be removed during velocity resolution
+            contact_velocity += acc_velocity;
+
+            contact_velocity
+        }
+
+        /// Calculates and sets the internal value for the desired delta
+        /// velocity.
+        fn calculate_desired_delta_velocity(&mut self, bodies: &[RigidBody], duration: Real) {
+            // Calculate the acceleration induced velocity accumulated this frame
+            let mut velocity_from_acc = 0 as Real;
+
+            for (i, body) in self.bodies.iter().enumerate() {
+                if let Some(body) = body.filter(|body| bodies[*body].is_awake()) {
+                    let velocity = bodies[body].get_last_frame_acceleration().dot(self.contact_normal) * duration;
+                    velocity_from_acc += if i == 0 { velocity } else { -velocity };
+                }
+            }
+
+            // If the velocity is very slow, limit the restitution
+            let restitution = if self.contact_velocity.x.abs() < VELOCITY_LIMIT {
+                0 as Real
+            } else {
+                self.restitution
+            };
+
+            // Combine the bounce velocity with the removed
+            // acceleration velocity.
+            self.desired_delta_velocity = -self.contact_velocity.x
+                - restitution * (self.contact_velocity.x - velocity_from_acc);
+        }
+
+        /// Updates the awake state of rigid bodies that are taking
+        /// place in the given contact. A body will be made awake if it
+        /// is in contact with a body that is awake. Collisions with the
+        /// world never cause a body to wake up.
+        fn match_awake_state(&self, bodies: &mut [RigidBody]) {
+            let (first, second) = match self.bodies {
+                [Some(first), Some(second)] => (first, second),
+                _ => return,
+            };
+
+            // Wake up only the sleeping one
+            if bodies[first].is_awake() != bodies[second].is_awake() {
+                if bodies[first].is_awake() {
+                    bodies[second].set_awake(true);
+                } else {
+                    bodies[first].set_awake(true);
+                }
+            }
+        }
+
+        /// Performs an inertia-weighted impulse based resolution of this
+        /// contact alone, returning the change in velocity and rotation
+        /// of each body.
+        fn apply_velocity_change(&self, bodies: &mut [RigidBody]) -> ([Vec3; 2], [Vec3; 2]) {
+            let mut velocity_change = [Vec3::default(); 2];
+            let mut rotation_change = [Vec3::default(); 2];
+
+            // We will calculate the impulse for each contact axis
+            let impulse_contact = if self.friction == 0 as Real {
+                // Use the short format for frictionless contacts
+                self.calculate_frictionless_impulse(bodies)
+            } else {
+                // Otherwise we may have impulses that aren't in the direction of the
+                // contact, so we need the more complex version.
+                self.calculate_friction_impulse(bodies)
+            };
+
+            // Convert impulse to world coordinates
+            let impulse = self.contact_to_world.transform(impulse_contact);
+
+            // Split in the impulse into linear and rotational components,
+            // in opposite directions for the second body
+            for (i, body) in self.bodies.iter().enumerate() {
+                if let Some(body) = body {
+                    let body = &mut bodies[*body];
+                    let impulse = if i == 0 { impulse } else { -impulse };
+                    let impulsive_torque = self.relative_contact_position[i].vec_prod(impulse);
+
+                    rotation_change[i] = body.get_inverse_inertia_tensor_world().transform(impulsive_torque);
+                    velocity_change[i] = impulse * body.get_inverse_mass();
+
+                    // Apply the changes
+                    body.add_velocity(velocity_change[i]);
+                    body.add_rotation(rotation_change[i]);
+                }
+            }
+
+            (velocity_change, rotation_change)
+        }
+
+        /// Calculates the impulse needed to resolve this contact, given
+        /// that the contact has no friction.
+        fn calculate_frictionless_impulse(&self, bodies: &[RigidBody]) -> Vec3 {
+            // Build a vector that shows the change in velocity in
+            // world space for a unit impulse in the direction of the contact
+            // normal.
+            let mut delta_velocity = 0 as Real;
+
+            for (i, body) in self.bodies.iter().enumerate() {
+                if let Some(body) = body {
+                    let body = &bodies[*body];
+                    let relative_position = self.relative_contact_position[i];
+
+                    let delta_vel_world = body.get_inverse_inertia_tensor_world()
+                        .transform(relative_position.vec_prod(self.contact_normal))
+                        .vec_prod(relative_position);
+
+                    // Work out the change in velocity in contact coordiantes,
+                    // adding the linear component of velocity change
+                    delta_velocity += delta_vel_world.dot(self.contact_normal) + body.get_inverse_mass();
+                }
+            }
+
+            // Neither body can be moved by the impulse
+            if delta_velocity <= 0 as Real {
+                return Vec3::default();
+            }
+
+            // Calculate the required size of the impulse
+            Vec3::new(self.desired_delta_velocity / delta_velocity, 0, 0)
+        }
+
+        /// Calculates the impulse needed to resolve this contact, given
+        /// that the contact has a non-zero coefficient of friction. A pair
+        /// of inertia tensors - one for each contact object - is used to
+        /// save on calculation.
+        fn calculate_friction_impulse(&self, bodies: &[RigidBody]) -> Vec3 {
+            let mut inverse_mass = 0 as Real;
+            let mut delta_vel_world = Matrix3::zero();
+
+            for (i, body) in self.bodies.iter().enumerate() {
+                if let Some(body) = body {
+                    let body = &bodies[*body];
+
+                    // The equivalent of a cross product in matrices is multiplication
+                    // by a skew symmetric matrix - we build the matrix for converting
+                    // between linear and angular quantities.
+                    let impulse_to_torque = Matrix3::from_skew_symmetric(self.relative_contact_position[i]);
+
+                    // Build the matrix to convert contact impulse to change in velocity
+                    // in world coordinates.
+                    delta_vel_world += impulse_to_torque * body.get_inverse_inertia_tensor_world()
+                        * impulse_to_torque * -1;
+
+                    inverse_mass += body.get_inverse_mass();
+                }
+            }
+
+            // Do a change of basis to convert into contact coordinates.
+            let mut delta_velocity = self.contact_to_world.transpose() * delta_vel_world * self.contact_to_world;
+
+            // Add in the linear velocity change
+            delta_velocity.data[0] += inverse_mass;
+            delta_velocity.data[4] += inverse_mass;
+            delta_velocity.data[8] += inverse_mass;
+
+            // Invert to get the impulse needed per unit velocity
+            let impulse_matrix = match delta_velocity.inverse() {
+                Some(impulse_matrix) => impulse_matrix,
+                None => return self.calculate_frictionless_impulse(bodies),
+            };
+
+            // Find the target velocities to kill
+            let vel_kill = Vec3::new(self.desired_delta_velocity, -self.contact_velocity.y, -self.contact_velocity.z);
+
+            // Find the impulse to kill target velocities
+            let mut impulse_contact = impulse_matrix.transform(vel_kill);
+
+            // Check for exceeding friction
+            let planar_impulse = (impulse_contact.y * impulse_contact.y + impulse_contact.z * impulse_contact.z).sqrt();
+
+            if planar_impulse > impulse_contact.x * self.friction {
+                // We need to use dynamic friction
+                impulse_contact.y /= planar_impulse;
+                impulse_contact.z /= planar_impulse;
+
+                let x = delta_velocity.data[0]
+                    + delta_velocity.data[1] * self.friction * impulse_contact.y
+                    + delta_velocity.data[2] * self.friction * impulse_contact.z;
+
+                impulse_contact.x = self.desired_delta_velocity / x;
+                impulse_contact.y *= self.friction * impulse_contact.x;
+                impulse_contact.z *= self.friction * impulse_contact.x;
+            }
+
+            impulse_contact
+        }
+
+        /// Performs an inertia weighted penetration resolution of this
+        /// contact alone, returning the linear and angular movement of
+        /// each body.
+        fn apply_position_change(&self, bodies: &mut [RigidBody], penetration: Real) -> ([Vec3; 2], [Vec3; 2]) {
+            let mut linear_change = [Vec3::default(); 2];
+            let mut angular_change = [Vec3::default(); 2];
+            let mut angular_inertia = [0 as Real; 2];
+            let mut linear_inertia = [0 as Real; 2];
+            let mut total_inertia = 0 as Real;
+
+            // We need to work out the inertia of each object in the direction
+            // of the contact normal, due to angular inertia only.
+            for (i, body) in self.bodies.iter().enumerate() {
+                if let Some(body) = body {
+                    let body = &bodies[*body];
+                    let relative_position = self.relative_contact_position[i];
+
+                    // Use the same procedure as for calculating frictionless
+                    // velocity change to work out the angular inertia.
+                    let angular_inertia_world = body.get_inverse_inertia_tensor_world()
+                        .transform(relative_position.vec_prod(self.contact_normal))
+                        .vec_prod(relative_position);
+
+                    angular_inertia[i] = angular_inertia_world.dot(self.contact_normal);
+
+                    // The linear component is simply the inverse mass
+                    linear_inertia[i] = body.get_inverse_mass();
+
+                    // Keep track of the total inertia from all components
+                    total_inertia += linear_inertia[i] + angular_inertia[i];
+                }
+            }
+
+            // Neither body can be moved
+            if total_inertia <= 0 as Real {
+                return (linear_change, angular_change);
+            }
+
+            // Loop through again calculating and applying the changes
+            for (i, body) in self.bodies.iter().enumerate() {
+                if let Some(body) = body {
+                    let body = &mut bodies[*body];
+                    let relative_position = self.relative_contact_position[i];
+
+                    // The linear and angular movements required are in proportion to
+                    // the two inverse inertias.
+                    let sign = if i == 0 { 1 as Real } else { -1 as Real };
+                    let mut angular_move = sign * penetration * (angular_inertia[i] / total_inertia);
+                    let mut linear_move = sign * penetration * (linear_inertia[i] / total_inertia);
+
+                    // To avoid angular projections that are too great (when mass is large
+                    // but inertia tensor is small) limit the angular move.
+                    let projection = relative_position
+                        + self.contact_normal * -relative_position.dot(self.contact_normal);
+
+                    // Use the small angle approximation for the sine of the angle (i.e.
+                    // the magnitude would be sine(angularLimit) * projection.magnitude
+                    // but we approximate sine(angularLimit) to angularLimit).
+                    let max_magnitude = ANGULAR_LIMIT * projection.magnitude();
+
+                    if angular_move.abs() > max_magnitude {
+                        let total_move = angular_move + linear_move;
+                        angular_move = angular_move.clamp(-max_magnitude, max_magnitude);
+                        linear_move = total_move - angular_move;
+                    }
+
+                    // We have the linear amount of movement required by turning
+                    // the rigid body (in angularMove[i]). We now need to
+                    // calculate the desired rotation to achieve that.
+                    if angular_move != 0 as Real {
+                        // Work out the direction we'd like to rotate in.
+                        let target_angular_direction = relative_position.vec_prod(self.contact_normal);
+
+                        // Work out the direction we'd need to rotate to achieve that
+                        angular_change[i] = body.get_inverse_inertia_tensor_world()
+                            .transform(target_angular_direction) * (angular_move / angular_inertia[i]);
+                    }
+
+                    // Velocity change is easier - it is just the linear movement
+                    // along the contact normal.
+                    linear_change[i] = self.contact_normal * linear_move;
+
+                    // Now we can start to apply the values we've calculated.
+                    // Apply the linear movement
+                    let mut position = body.get_position();
+                    position.add_scaled_vector(self.contact_normal, linear_move);
+                    body.set_position(position);
+
+                    // And the change in orientation
+                    let mut orientation = body.get_orientation();
+                    orientation.add_scaled_vector(angular_change[i], 1);
+                    body.set_orientation(orientation);
+                }
+            }
+
+            (linear_change, angular_change)
+        }
+    }
+
+    /// The contact resolution routine. One resolver instance
+    /// can be shared for the whole simulation, as long as you need
+    /// roughly the same parameters each time (which is normal).
+    ///
+    /// The resolver uses an iterative satisfaction algorithm: it loops
+    /// through each contact and tries to resolve it. Each contact is
+    /// resolved locally, which may in turn put other contacts in a worse
+    /// position. The algorithm then revisits other contacts and repeats
+    /// the process up to the number of iterations allowed, resolving the
+    /// most severe contact first each time. Velocity and penetration are
+    /// resolved in separate passes, with their own budgets.
+    #[derive(Debug, Clone)]
+    pub struct ContactResolver {
+        /// Holds the number of iterations to perform when resolving
+        /// velocity.
+        velocity_iterations: usize,
+        /// Holds the number of iterations to perform when resolving
+        /// position.
+        position_iterations: usize,
+        /// To avoid instability velocities smaller than this value are
+        /// considered to be zero. Too small and the simulation may be
+        /// unstable, too large and the bodies may interpenetrate
+        /// visually. A good starting point is the default of 0.01.
+        velocity_epsilon: Real,
+        /// To avoid instability penetrations smaller than this value are
+        /// considered to be not interpenetrating. Too small and the
+        /// simulation may be unstable, too large and the bodies may
+        /// interpenetrate visually. A good starting point is the default
+        /// of 0.01.
+        position_epsilon: Real,
+        /// Stores the number of velocity iterations used in the last call
+        /// to resolve contacts.
+        velocity_iterations_used: usize,
+        /// Stores the number of position iterations used in the last call
+        /// to resolve contacts.
+        position_iterations_used: usize,
+    }
+
+    impl ContactResolver {
+        /// Creates a new contact resolver with the given number of
+        /// iterations for each resolution pass.
+        pub fn new(velocity_iterations: usize, position_iterations: usize) -> Self {
+            ContactResolver {
+                velocity_iterations,
+                position_iterations,
+                velocity_epsilon: 0.01,
+                position_epsilon: 0.01,
+                velocity_iterations_used: 0,
+                position_iterations_used: 0,
+            }
+        }
+
+        /// Sets the number of iterations for each resolution stage.
+        pub fn set_iterations(&mut self, velocity_iterations: usize, position_iterations: usize) {
+            self.velocity_iterations = velocity_iterations;
+            self.position_iterations = position_iterations;
+        }
+
+        /// Sets the tolerance value for both velocity and position.
+        pub fn set_epsilon<T: AsReal, U: AsReal>(&mut self, velocity_epsilon: T, position_epsilon: U) {
+            self.velocity_epsilon = velocity_epsilon.as_real();
+            self.position_epsilon = position_epsilon.as_real();
+        }
+
+        /// Returns the number of velocity iterations used in the last
+        /// call to resolve contacts.
+        pub fn get_velocity_iterations_used(&self) -> usize {
+            self.velocity_iterations_used
+        }
+
+        /// Returns the number of position iterations used in the last
+        /// call to resolve contacts.
+        pub fn get_position_iterations_used(&self) -> usize {
+            self.position_iterations_used
+        }
+
+        /// Resolves a set of contacts for both penetration and velocity.
+        ///
+        /// Contacts that cannot interact with each other should be passed
+        /// to separate calls, as the resolution algorithm takes much
+        /// longer for lots of contacts than it does for the same number
+        /// of contacts in small sets.
+        ///
+        /// ### Arguments
+        /// * `contacts` - The contacts to be resolved.
+        /// * `bodies` - The rigid bodies referenced by the contacts.
+        /// * `duration` - The duration of the previous integration step.
+        pub fn resolve_contacts<T: AsReal>(&mut self, contacts: &mut [Contact], bodies: &mut [RigidBody], duration: T) {
+            let duration = duration.as_real();
+            self.velocity_iterations_used = 0;
+            self.position_iterations_used = 0;
+
+            // Make sure we have something to do.
+            if contacts.is_empty() {
+                return;
+            }
+
+            // Prepare the contacts for processing
+            for contact in contacts.iter_mut() {
+                contact.calculate_internals(bodies, duration);
+            }
+
+            // Resolve the interpenetration problems with the contacts.
+            self.adjust_positions(contacts, bodies);
+
+            // Resolve the velocity problems with the contacts.
+            self.adjust_velocities(contacts, bodies, duration);
+        }
+
+        /// Resolves the positional issues with the given array of
+        /// constraints, using the given number of iterations.
+        fn adjust_positions(&mut self, contacts: &mut [Contact], bodies: &mut [RigidBody]) {
+            // Iteratively resolve interpenetrations in order of severity.
+            while self.position_iterations_used < self.position_iterations {
+                // Find biggest penetration
+                let mut max = self.position_epsilon;
+                let mut index = contacts.len();
+
+                for (i, contact) in contacts.iter().enumerate() {
+                    if contact.penetration > max {
+                        max = contact.penetration;
+                        index = i;
+                    }
+                }
+
+                if index == contacts.len() {
+                    break;
+                }
+
+                // Resolve the penetration.
+                contacts[index].match_awake_state(bodies);
+                let (linear_change, angular_change) = contacts[index].apply_position_change(bodies, max);
+                let resolved = contacts[index].bodies;
+
+                // Again this action may have changed the penetration of other
+                // bodies, so we update contacts.
+                for contact in contacts.iter_mut() {
+                    // Check each body in the contact
+                    for b in 0..2 {
+                        let body = match contact.bodies[b] {
+                            Some(body) => body,
+                            None => continue,
+                        };
+
+                        // Check for a match with each body in the newly
+                        // resolved contact
+                        for d in 0..2 {
+                            if resolved[d] != Some(body) {
+                                continue;
+                            }
+
+                            let delta_position = linear_change[d]
+                                + angular_change[d].vec_prod(contact.relative_contact_position[b]);
+
+                            // The sign of the change is positive if we're
+                            // dealing with the second body in a contact
+                            // and negative otherwise (because we're
+                            // subtracting the resolution)..
+                            let change = delta_position.dot(contact.contact_normal);
+                            contact.penetration += if b == 1 { change } else { -change };
+                        }
+                    }
+                }
+
+                self.position_iterations_used += 1;
+            }
+        }
+
+        /// Resolves the velocity issues with the given array of
+        /// constraints, using the given number of iterations.
+        fn adjust_velocities(&mut self, contacts: &mut [Contact], bodies: &mut [RigidBody], duration: Real) {
+            // Iteratively handle impacts in order of severity.
+            while self.velocity_iterations_used < self.velocity_iterations {
+                // Find contact with maximum magnitude of probable velocity change.
+                let mut max = self.velocity_epsilon;
+                let mut index = contacts.len();
+
+                for (i, contact) in contacts.iter().enumerate() {
+                    if contact.desired_delta_velocity > max {
+                        max = contact.desired_delta_velocity;
+                        index = i;
+                    }
+                }
+
+                if index == contacts.len() {
+                    break;
+                }
+
+                // Do the resolution on the contact that came out top.
+                contacts[index].match_awake_state(bodies);
+                let (velocity_change, rotation_change) = contacts[index].apply_velocity_change(bodies);
+                let resolved = contacts[index].bodies;
+
+                // With the change in velocity of the two bodies, the update of
+                // contact velocities means that some of the relative closing
+                // velocities need recomputing.
+                for contact in contacts.iter_mut() {
+                    // Check each body in the contact
+                    for b in 0..2 {
+                        let body = match contact.bodies[b] {
+                            Some(body) => body,
+                            None => continue,
+                        };
+
+                        // Check for a match with each body in the newly
+                        // resolved contact
+                        for d in 0..2 {
+                            if resolved[d] != Some(body) {
+                                continue;
+                            }
+
+                            let delta_vel = velocity_change[d]
+                                + rotation_change[d].vec_prod(contact.relative_contact_position[b]);
+
+                            // The sign of the change is negative if we're dealing
+                            // with the second body in a contact.
+                            let change = contact.contact_to_world.transform_transpose(delta_vel);
+                            contact.contact_velocity += if b == 1 { -change } else { change };
+                            contact.calculate_desired_delta_velocity(bodies, duration);
+                        }
+                    }
+                }
+
+                self.velocity_iterations_used += 1;
+            }
+        }
+    }
+}
+
+pub mod collide_coarse {
+    use std::collections::{BTreeSet, HashMap};
+    use super::precision::*;
+    use super::core::*;
+
+    /// A bounding volume used by the coarse collision detection to
+    /// quickly discard pairs of objects that can not be in contact.
+    pub trait BoundingVolume: Copy {
+        /// Creates the volume that encloses the two given volumes.
+        fn enclose(one: &Self, two: &Self) -> Self;
+        /// Checks if the bounding volume overlaps with the other given
+        /// bounding volume.
+        fn overlaps(&self, other: &Self) -> bool;
+        /// Returns the volume of this bounding volume. This is used
+        /// to calculate how to recurse into the bounding volume tree.
+        fn get_size(&self) -> Real;
+        /// Reports how much this bounding volume would have to grow
+        /// by to incorporate the given bounding volume.
+        fn get_growth(&self, other: &Self) -> Real;
+        /// Returns the centre of the bounding volume.
+        fn get_centre(&self) -> Vec3;
+    }
+
+    /// Represents a bounding sphere that can be tested for overlap.
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub struct BoundingSphere {
+        centre: Vec3,
+        radius: Real,
+    }
+
+    impl BoundingSphere {
+        /// Creates a new bounding sphere at the given centre and radius.
+        pub fn new<T: AsReal>(centre: Vec3, radius: T) -> Self {
+            BoundingSphere { centre, radius: radius.as_real() }
+        }
+
+        /// Returns the radius of the sphere.
+        pub fn get_radius(&self) -> Real {
+            self.radius
+        }
+    }
+
+    impl BoundingVolume for BoundingSphere {
+        /// Creates a bounding sphere to enclose the two given bounding
+        /// spheres.
+        fn enclose(one: &Self, two: &Self) -> Self {
+            let centre_offset = two.centre - one.centre;
+            let distance = centre_offset.square_magnitude();
+            let radius_diff = two.radius - one.radius;
+
+            // Check if the larger sphere encloses the small one
+            if radius_diff * radius_diff >= distance {
+                return if one.radius > two.radius { *one } else { *two };
+            }
+
+            // Otherwise we need to work with partially
+            // overlapping spheres
+            let distance = distance.sqrt();
+            let radius = (distance + one.radius + two.radius) * 0.5;
+
+            // The new centre is based on one's centre, moved towards
+            // two's centre by an ammount proportional to the spheres'
+            // radii.
+            let mut centre = one.centre;
+
+            if distance > 0 as Real {
+                centre += centre_offset * ((radius - one.radius) / distance);
+            }
+
+            BoundingSphere { centre, radius }
+        }
+
+        fn overlaps(&self, other: &Self) -> bool {
+            let distance_squared = (self.centre - other.centre).square_magnitude();
+            distance_squared < (self.radius + other.radius) * (self.radius + other.radius)
+        }
+
+        fn get_size(&self) -> Real {
+            4.0 / 3.0 * REAL_PI * self.radius * self.radius * self.radius
+        }
+
+        /// Reports the growth of the surface of the sphere, which is
+        /// proportional to the square of its radius.
+        fn get_growth(&self, other: &Self) -> Real {
+            let new_sphere = Self::enclose(self, other);
+            new_sphere.radius * new_sphere.radius - self.radius * self.radius
+        }
+
+        fn get_centre(&self) -> Vec3 {
+            self.centre
+        }
+    }
+
+    /// Represents an axis-aligned bounding box that can be tested for
+    /// overlap.
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub struct Aabb {
+        min: Vec3,
+        max: Vec3,
+    }
+
+    impl Aabb {
+        /// Creates a new bounding box between the given corners, which
+        /// must be ordered on every axis.
+        pub fn new(min: Vec3, max: Vec3) -> Self {
+            Aabb { min, max }
+        }
+
+        /// Creates a new bounding box at the given centre with the given
+        /// half-sizes along each axis.
+        pub fn from_centre_and_half_size(centre: Vec3, half_size: Vec3) -> Self {
+            Aabb { min: centre - half_size, max: centre + half_size }
+        }
+
+        /// Returns the corner of the box with the lowest coordinates.
+        pub fn get_min(&self) -> Vec3 {
+            self.min
+        }
+
+        /// Returns the corner of the box with the highest coordinates.
+        pub fn get_max(&self) -> Vec3 {
+            self.max
+        }
+
+        /// Returns the area of the surface of the box.
+        pub fn get_surface_area(&self) -> Real {
+            let size = self.max - self.min;
+            2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+        }
+    }
+
+    impl BoundingVolume for Aabb {
+        fn enclose(one: &Self, two: &Self) -> Self {
+            Aabb {
+                min: Vec3::new(one.min.x.min(two.min.x), one.min.y.min(two.min.y), one.min.z.min(two.min.z)),
+                max: Vec3::new(one.max.x.max(two.max.x), one.max.y.max(two.max.y), one.max.z.max(two.max.z)),
+            }
+        }
+
+        fn overlaps(&self, other: &Self) -> bool {
+            self.min.x <= other.max.x && other.min.x <= self.max.x &&
+            self.min.y <= other.max.y && other.min.y <= self.max.y &&
+            self.min.z <= other.max.z && other.min.z <= self.max.z
+        }
+
+        fn get_size(&self) -> Real {
+            let size = self.max - self.min;
+            size.x * size.y * size.z
+        }
+
+        /// Reports the growth of the surface area of the box, which is
+        /// a better estimate of the cost of a node than its volume.
+        fn get_growth(&self, other: &Self) -> Real {
+            Self::enclose(self, other).get_surface_area() - self.get_surface_area()
+        }
+
+        fn get_centre(&self) -> Vec3 {
+            (self.min + self.max) * 0.5
+        }
+    }
+
+    /// Stores a potential contact to check later, given by the indices
+    /// of the two objects whose bounding volumes overlap.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct PotentialContact {
+        /// Holds the objects that might be in contact.
+        pub bodies: [usize; 2],
+    }
+
+    /// A node in a bounding volume hierarchy. This is a binary tree
+    /// where each node has a bounding volume enclosing everything
+    /// below it, and the leaves hold the objects to check.
+    #[derive(Debug, Clone)]
+    pub struct BVHNode<B: BoundingVolume> {
+        /// Holds the child nodes of this node, for branch nodes.
+        children: Option<Box<[BVHNode<B>; 2]>>,
+        /// Holds a single bounding volume encompassing all the
+        /// descendents of this node.
+        volume: B,
+        /// Holds the index of the object at this node of the hierarchy.
+        /// Only leaf nodes can have an object defined.
+        body: Option<usize>,
+    }
+
+    impl<B: BoundingVolume> BVHNode<B> {
+        /// Creates a new leaf node holding the given object.
+        pub fn new(body: usize, volume: B) -> Self {
+            BVHNode { children: None, volume, body: Some(body) }
+        }
+
+        /// Creates a balanced hierarchy holding the given objects, or
+        /// `None` if there are none.
+        pub fn from_leaves(leaves: Vec<(usize, B)>) -> Option<Self> {
+            if leaves.is_empty() {
+                None
+            } else {
+                Some(Self::build(leaves))
+            }
+        }
+
+        /// Creates a branch node holding the two given nodes.
+        fn from_children(one: BVHNode<B>, two: BVHNode<B>) -> Self {
+            BVHNode {
+                volume: B::enclose(&one.volume, &two.volume),
+                children: Some(Box::new([one, two])),
+                body: None,
+            }
+        }
+
+        /// Builds a hierarchy from the given non-empty set of objects,
+        /// splitting them in halves along the axis where their centres
+        /// are most spread out.
+        fn build(mut leaves: Vec<(usize, B)>) -> Self {
+            if leaves.len() == 1 {
+                let (body, volume) = leaves[0];
+                return Self::new(body, volume);
+            }
+
+            let mut min = Vec3::new(REAL_MAX, REAL_MAX, REAL_MAX);
+            let mut max = -min;
+
+            for (_, volume) in leaves.iter() {
+                let centre = volume.get_centre();
+                min = Vec3::new(min.x.min(centre.x), min.y.min(centre.y), min.z.min(centre.z));
+                max = Vec3::new(max.x.max(centre.x), max.y.max(centre.y), max.z.max(centre.z));
+            }
+
+            let spread = max - min;
+            let component = |vector: Vec3| {
+                if spread.x >= spread.y && spread.x >= spread.z {
+                    vector.x
+                } else if spread.y >= spread.z {
+                    vector.y
+                } else {
+                    vector.z
+                }
+            };
+
+            leaves.sort_by(|(_, one), (_, two)| {
+                component(one.get_centre()).total_cmp(&component(two.get_centre()))
+            });
+
+            let other_half = leaves.split_off(leaves.len() / 2);
+            Self::from_children(Self::build(leaves), Self::build(other_half))
+        }
+
+        /// Checks if this node is at the bottom of the hierarchy.
+        pub fn is_leaf(&self) -> bool {
+            self.children.is_none()
+        }
+
+        /// Returns the object of the node, for leaf nodes.
+        pub fn get_body(&self) -> Option<usize> {
+            self.body
+        }
+
+        /// Returns the bounding volume enclosing everything below
+        /// this node.
+        pub fn get_volume(&self) -> B {
+            self.volume
+        }
+
+        /// Returns the number of levels of the hierarchy below and
+        /// including this node.
+        pub fn get_depth(&self) -> usize {
+            match &self.children {
+                Some(children) => 1 + children[0].get_depth().max(children[1].get_depth()),
+                None => 1,
+            }
+        }
+
+        /// Inserts the given object, with the given bounding volume,
+        /// into the hierarchy. It is added below the child whose volume
+        /// would grow the least.
+        pub fn insert(&mut self, body: usize, volume: B) {
+            match &mut self.children {
+                // If we are a leaf, then the only option is to spawn two
+                // new children and place the new object in one.
+                None => {
+                    let old = Self { children: None, volume: self.volume, body: self.body.take() };
+                    *self = Self::from_children(old, Self::new(body, volume));
+                },
+                // Otherwise we need to work out which child gets to keep
+                // the inserted object. We give it to whoever would grow the
+                // least to incorporate it.
+                Some(children) => {
+                    if children[0].volume.get_growth(&volume) < children[1].volume.get_growth(&volume) {
+                        children[0].insert(body, volume);
+                    } else {
+                        children[1].insert(body, volume);
+                    }
+
+                    // Make sure our bounding volume is still correct
+                    self.volume = B::enclose(&children[0].volume, &children[1].volume);
+                },
+            }
+        }
+
+        /// Removes every leaf holding the given object from the hierarchy,
+        /// returning what is left of it, or `None` if nothing is left.
+        /// The sibling of a removed leaf takes the place of their parent.
+        pub fn remove(self, body: usize) -> Option<Self> {
+            match self.children {
+                None if self.body == Some(body) => None,
+                None => Some(self),
+                Some(children) => {
+                    let [one, two] = *children;
+
+                    match (one.remove(body), two.remove(body)) {
+                        (Some(one), Some(two)) => Some(Self::from_children(one, two)),
+                        (Some(node), None) | (None, Some(node)) => Some(node),
+                        (None, None) => None,
+                    }
+                },
+            }
+        }
+
+        /// Rebuilds the hierarchy below this node so that it is balanced,
+        /// which is useful after many insertions and removals.
+        pub fn rebalance(&mut self) {
+            let mut leaves = Vec::new();
+            self.collect_leaves(&mut leaves);
+            *self = Self::build(leaves);
+        }
+
+        /// Adds the objects and volumes of the leaves below this node to
+        /// the given list.
+        fn collect_leaves(&self, leaves: &mut Vec<(usize, B)>) {
+            match &self.children {
+                Some(children) => {
+                    children[0].collect_leaves(leaves);
+                    children[1].collect_leaves(leaves);
+                },
+                None => {
+                    if let Some(body) = self.body {
+                        leaves.push((body, self.volume));
+                    }
+                },
+            }
+        }
+
+        /// Returns the pairs of objects below this node whose bounding
+        /// volumes overlap, up to the given limit. These are the only
+        /// pairs that need to go through the fine collision detection.
+        pub fn get_potential_contacts(&self, limit: usize) -> Vec<PotentialContact> {
+            let mut contacts = Vec::new();
+            self.collect_potential_contacts(&mut contacts, limit);
+            contacts
+        }
+
+        /// Returns the objects below this node whose bounding volumes
+        /// overlap the given volume.
+        pub fn get_overlapping(&self, volume: &B) -> Vec<usize> {
+            let mut objects = Vec::new();
+            self.collect_overlapping(volume, &mut objects);
+            objects
+        }
+
+        /// Adds the objects below this node whose bounding volumes overlap
+        /// the given volume to the given list.
+        fn collect_overlapping(&self, volume: &B, objects: &mut Vec<usize>) {
+            if !self.volume.overlaps(volume) {
+                return;
+            }
+
+            match &self.children {
+                Some(children) => {
+                    children[0].collect_overlapping(volume, objects);
+                    children[1].collect_overlapping(volume, objects);
+                },
+                None => objects.extend(self.body),
+            }
+        }
+
+        /// Adds the potential contacts between the objects below this
+        /// node to the given list.
+        fn collect_potential_contacts(&self, contacts: &mut Vec<PotentialContact>, limit: usize) {
+            if let Some(children) = &self.children {
+                children[0].collect_potential_contacts(contacts, limit);
+                children[1].collect_potential_contacts(contacts, limit);
+                children[0].collect_potential_contacts_with(&children[1], contacts, limit);
+            }
+        }
+
+        /// Adds the potential contacts between the objects below this node
+        /// and the ones below the given other node to the given list.
+        fn collect_potential_contacts_with(&self, other: &Self, contacts: &mut Vec<PotentialContact>, limit: usize) {
+            // Early out if we don't overlap or if we have no room
+            // to report contacts
+            if contacts.len() >= limit || !self.volume.overlaps(&other.volume) {
+                return;
+            }
+
+            match (&self.children, &other.children) {
+                // If we're both at leaf nodes, then we have a potential contact
+                (None, None) => {
+                    if let (Some(one), Some(two)) = (self.body, other.body) {
+                        contacts.push(PotentialContact { bodies: [one, two] });
+                    }
+                },
+                // Determine which node to descend into. If either is
+                // a leaf, then we descend the other. If both are branches,
+                // then we use the one with the largest size.
+                (Some(children), None) => {
+                    children[0].collect_potential_contacts_with(other, contacts, limit);
+                    children[1].collect_potential_contacts_with(other, contacts, limit);
+                },
+                (Some(children), Some(_)) if self.volume.get_size() >= other.volume.get_size() => {
+                    children[0].collect_potential_contacts_with(other, contacts, limit);
+                    children[1].collect_potential_contacts_with(other, contacts, limit);
+                },
+                (_, Some(children)) => {
+                    self.collect_potential_contacts_with(&children[0], contacts, limit);
+                    self.collect_potential_contacts_with(&children[1], contacts, limit);
+                },
+            }
+        }
+    }
+
+    /// The coarse collision detection stage, which finds the pairs of
+    /// objects whose bounding boxes overlap so that only those go
+    /// through the fine collision detection.
+    pub trait BroadPhase {
+        /// Updates the broad phase with the bounding boxes of all the
+        /// objects, indexed as the objects themselves. This is called at
+        /// each step, after the objects have moved.
+        fn update(&mut self, volumes: &[Aabb]);
+        /// Returns the pairs of objects whose bounding boxes overlap, up
+        /// to the given limit.
+        fn get_potential_contacts(&self, limit: usize) -> Vec<PotentialContact>;
+        /// Returns the objects whose bounding boxes overlap the given
+        /// box, which is used to answer spatial queries.
+        fn query(&self, volume: &Aabb) -> Vec<usize>;
+    }
+
+    /// A broad phase that tests every pair of objects. This is the
+    /// fastest choice for a handful of objects only.
+    #[derive(Debug, Clone, Default)]
+    pub struct BruteForceBroadPhase {
+        /// Holds the bounding boxes of the objects.
+        volumes: Vec<Aabb>,
+    }
+
+    impl BruteForceBroadPhase {
+        /// Creates an empty brute force broad phase.
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    impl BroadPhase for BruteForceBroadPhase {
+        fn update(&mut self, volumes: &[Aabb]) {
+            self.volumes.clear();
+            self.volumes.extend_from_slice(volumes);
+        }
+
+        fn get_potential_contacts(&self, limit: usize) -> Vec<PotentialContact> {
+            let mut contacts = Vec::new();
+
+            for (i, one) in self.volumes.iter().enumerate() {
+                for (j, two) in self.volumes.iter().enumerate().skip(i + 1) {
+                    if contacts.len() >= limit {
+                        return contacts;
+                    }
+
+                    if one.overlaps(two) {
+                        contacts.push(PotentialContact { bodies: [i, j] });
+                    }
+                }
+            }
+
+            contacts
+        }
+
+        fn query(&self, volume: &Aabb) -> Vec<usize> {
+            (0..self.volumes.len()).filter(|index| self.volumes[*index].overlaps(volume)).collect()
+        }
+    }
+
+    /// A broad phase that rebuilds a balanced bounding volume hierarchy
+    /// of the objects at each update. This is the best choice for
+    /// objects of very different sizes.
+    #[derive(Debug, Clone, Default)]
+    pub struct BVHBroadPhase {
+        /// Holds the root of the hierarchy, if there are any objects.
+        root: Option<BVHNode<Aabb>>,
+    }
+
+    impl BVHBroadPhase {
+        /// Creates an empty hierarchy broad phase.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Returns the root of the hierarchy built at the last update.
+        pub fn get_root(&self) -> Option<&BVHNode<Aabb>> {
+            self.root.as_ref()
+        }
+    }
+
+    impl BroadPhase for BVHBroadPhase {
+        fn update(&mut self, volumes: &[Aabb]) {
+            self.root = BVHNode::from_leaves(volumes.iter().copied().enumerate().collect());
+        }
+
+        fn get_potential_contacts(&self, limit: usize) -> Vec<PotentialContact> {
+            match &self.root {
+                Some(root) => root.get_potential_contacts(limit),
+                None => Vec::new(),
+            }
+        }
+
+        fn query(&self, volume: &Aabb) -> Vec<usize> {
+            match &self.root {
+                Some(root) => root.get_overlapping(volume),
+                None => Vec::new(),
+            }
+        }
+    }
+
+    /// A broad phase that hashes the objects into a uniform grid of
+    /// cubic cells, so that only the objects sharing a cell are tested.
+    /// This is the best choice for many objects of similar size, which
+    /// should be about the size of a cell.
+    ///
+    /// The grid is updated incrementally: only the objects that moved
+    /// to different cells are rehashed. Objects overlapping more than
+    /// `MAX_CELLS_PER_OBJECT` cells are not hashed at all, but tested
+    /// against every other object instead.
+    #[derive(Debug, Clone)]
+    pub struct SpatialHashGrid {
+        /// Holds the length of the edges of the cells.
+        cell_size: Real,
+        /// Holds the objects overlapping each non-empty cell.
+        cells: HashMap<[i32; 3], Vec<usize>>,
+        /// Holds the objects too large to be hashed into the cells.
+        oversized: BTreeSet<usize>,
+        /// Holds the bounding boxes of the objects.
+        volumes: Vec<Aabb>,
+        /// Holds the first and last cells overlapped by each object.
+        ranges: Vec<([i32; 3], [i32; 3])>,
+    }
+
+    /// The largest number of cells an object can be hashed into. Larger
+    /// objects would make the grid slow and take a lot of memory.
+    pub const MAX_CELLS_PER_OBJECT: u64 = 512;
+
+    impl SpatialHashGrid {
+        /// Creates an empty grid with cells of the given size, which
+        /// must be positive.
+        pub fn new<T: AsReal>(cell_size: T) -> Self {
+            let cell_size = cell_size.as_real();
+
+            if cell_size <= 0 as Real {
+                panic!("Cell size must be positive!");
+            }
+
+            SpatialHashGrid {
+                cell_size,
+                cells: HashMap::new(),
+                oversized: BTreeSet::new(),
+                volumes: Vec::new(),
+                ranges: Vec::new(),
+            }
+        }
+
+        /// Returns the length of the edges of the cells.
+        pub fn get_cell_size(&self) -> Real {
+            self.cell_size
+        }
+
+        /// Returns the coordinates of the cell containing the given
+        /// position.
+        pub fn get_cell(&self, position: Vec3) -> [i32; 3] {
+            [
+                (position.x / self.cell_size).floor() as i32,
+                (position.y / self.cell_size).floor() as i32,
+                (position.z / self.cell_size).floor() as i32,
+            ]
+        }
+
+        /// Returns the number of non-empty cells.
+        pub fn get_cell_count(&self) -> usize {
+            self.cells.len()
+        }
+
+        /// Returns the number of cells in the given range.
+        fn cell_count(range: ([i32; 3], [i32; 3])) -> u64 {
+            let (first, last) = range;
+
+            (0..3)
+                .map(|axis| (last[axis] as i64 - first[axis] as i64 + 1).max(0) as u64)
+                .fold(1u64, |count, cells| count.saturating_mul(cells))
+        }
+
+        /// Calls the given function with every cell of the given range.
+        fn for_each_cell<F: FnMut([i32; 3])>(range: ([i32; 3], [i32; 3]), mut f: F) {
+            let (first, last) = range;
+
+            for x in first[0]..=last[0] {
+                for y in first[1]..=last[1] {
+                    for z in first[2]..=last[2] {
+                        f([x, y, z]);
+                    }
+                }
+            }
+        }
 
-    impl<T: AsReal> Mul<T> for Vec3 {
-        type Output = Self;
+        /// Removes the object at the given index from the cells it was
+        /// hashed into.
+        fn remove_from_cells(&mut self, index: usize) {
+            if self.oversized.remove(&index) {
+                return;
+            }
 
-        fn mul(self, other: T) -> Self {
-            let other = other.as_real();
+            let cells = &mut self.cells;
 
-            Self::new(
-                self.x * other,
-                self.y * other,
-                self.z * other
-            )
+            Self::for_each_cell(self.ranges[index], |cell| {
+                if let Some(objects) = cells.get_mut(&cell) {
+                    objects.retain(|object| *object != index);
+
+                    if objects.is_empty() {
+                        cells.remove(&cell);
+                    }
+                }
+            });
         }
     }
 
-    impl<T: AsReal> MulAssign<T> for Vec3 {
-        fn mul_assign(&mut self, other: T) {
-            let other = other.as_real();
+    impl BroadPhase for SpatialHashGrid {
+        fn update(&mut self, volumes: &[Aabb]) {
+            // Forget the objects that are gone
+            for index in volumes.len()..self.ranges.len() {
+                self.remove_from_cells(index);
+            }
 
-            *self = Self::new(
-                self.x * other,
-                self.y * other,
-                self.z * other
-            )
+            self.ranges.truncate(volumes.len());
+
+            for (index, volume) in volumes.iter().enumerate() {
+                let range = (self.get_cell(volume.get_min()), self.get_cell(volume.get_max()));
+
+                if index < self.ranges.len() {
+                    // Objects staying in the same cells are left alone
+                    if self.ranges[index] == range {
+                        continue;
+                    }
+
+                    self.remove_from_cells(index);
+                    self.ranges[index] = range;
+                } else {
+                    self.ranges.push(range);
+                }
+
+                if Self::cell_count(range) > MAX_CELLS_PER_OBJECT {
+                    self.oversized.insert(index);
+                    continue;
+                }
+
+                let cells = &mut self.cells;
+                Self::for_each_cell(range, |cell| cells.entry(cell).or_default().push(index));
+            }
+
+            self.volumes.clear();
+            self.volumes.extend_from_slice(volumes);
         }
-    }
 
-    impl Neg for Vec3 {
-        type Output = Self;
+        fn get_potential_contacts(&self, limit: usize) -> Vec<PotentialContact> {
+            let mut contacts = Vec::new();
 
-        fn neg(self) -> Self::Output {
-            Vec3::new(-self.x, -self.y, -self.z)
+            for (cell, objects) in self.cells.iter() {
+                for (i, one) in objects.iter().enumerate() {
+                    for two in objects.iter().skip(i + 1) {
+                        // Objects can share many cells, so each pair is only
+                        // reported in the first cell they have in common
+                        let (one_first, two_first) = (self.ranges[*one].0, self.ranges[*two].0);
+                        let first = [
+                            one_first[0].max(two_first[0]),
+                            one_first[1].max(two_first[1]),
+                            one_first[2].max(two_first[2]),
+                        ];
+
+                        if first == *cell && self.volumes[*one].overlaps(&self.volumes[*two]) {
+                            contacts.push(PotentialContact { bodies: [*one.min(two), *one.max(two)] });
+                        }
+                    }
+                }
+            }
+
+            // Oversized objects are tested against all the others, each
+            // pair of them being tested only once
+            for one in self.oversized.iter() {
+                for two in 0..self.volumes.len() {
+                    if two == *one || (two < *one && self.oversized.contains(&two)) {
+                        continue;
+                    }
+
+                    if self.volumes[*one].overlaps(&self.volumes[two]) {
+                        contacts.push(PotentialContact { bodies: [two.min(*one), two.max(*one)] });
+                    }
+                }
+            }
+
+            // The cells are visited in no particular order, so sort the
+            // pairs to keep the result the same from run to run
+            contacts.sort_unstable_by_key(|contact| contact.bodies);
+            contacts.truncate(limit);
+            contacts
         }
-    }
 
-    impl Vec3 {
-        /// Invert all the elements of the vector
-        pub fn invert(&mut self) {
-            *self = -(*self);
+        fn query(&self, volume: &Aabb) -> Vec<usize> {
+            let (first, last) = (self.get_cell(volume.get_min()), self.get_cell(volume.get_max()));
+            let mut objects: Vec<usize> = self.oversized.iter().copied().collect();
+
+            // Large boxes are faster to check against the non-empty cells
+            if Self::cell_count((first, last)) > self.cells.len() as u64 {
+                for (cell, cell_objects) in self.cells.iter() {
+                    if (0..3).all(|axis| first[axis] <= cell[axis] && cell[axis] <= last[axis]) {
+                        objects.extend_from_slice(cell_objects);
+                    }
+                }
+            } else {
+                Self::for_each_cell((first, last), |cell| {
+                    if let Some(cell_objects) = self.cells.get(&cell) {
+                        objects.extend_from_slice(cell_objects);
+                    }
+                });
+            }
+
+            // Objects can be in many cells
+            objects.sort_unstable();
+            objects.dedup();
+            objects.retain(|object| self.volumes[*object].overlaps(volume));
+            objects
         }
+    }
 
-        /// Returns the magnitude of the vector
-        pub fn magnitude(&self) -> Real {
-            (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
+    /// The axes along which a sweep-and-prune broad phase sorts the
+    /// objects.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+    pub enum SweepAxis {
+        /// Sorts along the x axis only.
+        X,
+        /// Sorts along the y axis only.
+        Y,
+        /// Sorts along the z axis only.
+        Z,
+        /// Sorts along the three axes, so that the tracked pairs are
+        /// exactly the overlapping boxes.
+        #[default]
+        All,
+    }
+
+    /// A change in the set of overlapping pairs tracked by a
+    /// sweep-and-prune broad phase.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum PairEvent {
+        /// The two objects started overlapping.
+        Added([usize; 2]),
+        /// The two objects stopped overlapping.
+        Removed([usize; 2]),
+    }
+
+    /// One end of the interval covered by an object along an axis.
+    #[derive(Debug, Copy, Clone)]
+    struct Endpoint {
+        /// Holds the coordinate of the endpoint along the axis.
+        value: Real,
+        /// Holds the index of the object.
+        object: usize,
+        /// True for the lower end of the interval.
+        is_min: bool,
+    }
+
+    impl Endpoint {
+        /// Checks if this endpoint should be sorted after the other one.
+        /// At the same coordinate lower ends come first, so that touching
+        /// intervals overlap.
+        fn is_after(&self, other: &Endpoint) -> bool {
+            self.value > other.value || (self.value == other.value && !self.is_min && other.is_min)
         }
+    }
 
-        /// Returns the square of the magnitude of the vector
-        pub fn square_magnitude(&self) -> Real {
-            self.x.powi(2) + self.y.powi(2) + self.z.powi(2)
+    /// Returns the coordinate of the vector along the given axis.
+    fn component(vector: Vec3, axis: usize) -> Real {
+        match axis {
+            0 => vector.x,
+            1 => vector.y,
+            _ => vector.z,
         }
+    }
 
-        /// Normalize the elements of the vector
-        pub fn normalize(&mut self) {
-            let m = self.magnitude();
+    /// A broad phase that keeps the ends of the boxes of the objects
+    /// sorted along one or all axes. The lists are kept from a step to
+    /// the next and sorted again with an insertion sort, which is very
+    /// fast when the objects move little between steps. The pairs of
+    /// objects overlapping on the sorted axes are tracked as the ends
+    /// swap places, and the changes are reported as events.
+    #[derive(Debug, Clone)]
+    pub struct SweepAndPrune {
+        /// Holds the indices of the sorted axes.
+        axes: Vec<usize>,
+        /// Holds the sorted endpoints for each of the sorted axes.
+        endpoints: Vec<Vec<Endpoint>>,
+        /// Holds the bounding boxes of the objects.
+        volumes: Vec<Aabb>,
+        /// Holds the pairs of objects overlapping on the sorted axes,
+        /// with the lowest index first.
+        pairs: BTreeSet<[usize; 2]>,
+        /// Holds the changes to the pairs in the last update.
+        events: Vec<PairEvent>,
+    }
 
-            if m > 0.0 {
-                *self *= 1.0 / m;
+    impl SweepAndPrune {
+        /// Creates an empty sweep-and-prune broad phase sorting along
+        /// the given axes.
+        pub fn new(axis: SweepAxis) -> Self {
+            let axes = match axis {
+                SweepAxis::X => vec![0],
+                SweepAxis::Y => vec![1],
+                SweepAxis::Z => vec![2],
+                SweepAxis::All => vec![0, 1, 2],
+            };
+
+            SweepAndPrune {
+                endpoints: vec![Vec::new(); axes.len()],
+                axes,
+                volumes: Vec::new(),
+                pairs: BTreeSet::new(),
+                events: Vec::new(),
             }
         }
 
-        /// Adds elements of the vector to the scaled correspondend element of
-        /// another vector, given as arguments.
-        ///
-        /// ### Arguments
-        /// * `other` - The vector to be scaled and added
-        /// * `scale` - The scale factor
-        pub fn add_scaled_vector<T: AsReal>(&mut self, other: Vec3, scale: T) {
-            *self += other * scale.as_real();
+        /// Returns the changes to the overlapping pairs made by the last
+        /// update.
+        pub fn get_events(&self) -> &[PairEvent] {
+            &self.events
         }
 
-        /// Returns the element-wise multiplication of this vector
-        /// and the argument vector.
-        pub fn elementwise_prod(&self, other: Vec3) -> Vec3 {
-            Vec3::new(
-                self.x * other.x,
-                self.y * other.y,
-                self.z * other.z,
-            )
+        /// Returns the number of pairs overlapping on the sorted axes.
+        pub fn get_pair_count(&self) -> usize {
+            self.pairs.len()
         }
 
-        /// Element-wise operation between this vector and the
-        /// given argument vector, the result is then replaced
-        /// to the repective elements of this vector.
-        pub fn elementwise_prod_assign(&mut self, other: Vec3) {
-            *self = self.elementwise_prod(other);
+        /// Records that the two objects started or stopped overlapping
+        /// along one of the sorted axes. A pair is only added once the
+        /// objects overlap on all of them.
+        fn overlap_changed(&mut self, one: usize, two: usize, begins: bool) {
+            let pair = [one.min(two), one.max(two)];
+
+            if begins {
+                let (one, two) = (&self.volumes[one], &self.volumes[two]);
+                let overlaps = self.axes.iter().all(|axis| {
+                    component(one.get_min(), *axis) <= component(two.get_max(), *axis) &&
+                    component(two.get_min(), *axis) <= component(one.get_max(), *axis)
+                });
+
+                if overlaps && self.pairs.insert(pair) {
+                    self.events.push(PairEvent::Added(pair));
+                }
+            } else if self.pairs.remove(&pair) {
+                self.events.push(PairEvent::Removed(pair));
+            }
         }
 
-        /// Returns the scalar product between this vector
-        /// and the one give as argument.
-        pub fn dot(&self, other: Vec3) -> Real {
-            self.x * other.x + self.y * other.y + self.z * other.z
+        /// Sorts the endpoints of the given sorted axis again, updating
+        /// the pairs as the ends of different objects swap places.
+        fn sort_axis(&mut self, slot: usize) {
+            for i in 1..self.endpoints[slot].len() {
+                let mut j = i;
+
+                while j > 0 && self.endpoints[slot][j - 1].is_after(&self.endpoints[slot][j]) {
+                    let moving = self.endpoints[slot][j];
+                    let passed = self.endpoints[slot][j - 1];
+
+                    // A lower end moving below an upper end starts an
+                    // overlap, an upper end moving below a lower end
+                    // stops one.
+                    if moving.object != passed.object && moving.is_min != passed.is_min {
+                        self.overlap_changed(moving.object, passed.object, moving.is_min);
+                    }
+
+                    self.endpoints[slot].swap(j - 1, j);
+                    j -= 1;
+                }
+            }
         }
+    }
 
-        /// Returns the vectorial product between this vector
-        /// and the given one.
-        pub fn vec_prod(&self, other: Vec3) -> Vec3 {
-            Vec3::new(
-                self.y * other.z - self.z * other.y,
-                self.z * other.x - self.x * other.z,
-                self.x * other.y - self.y * other.x
-            )
+    impl BroadPhase for SweepAndPrune {
+        fn update(&mut self, volumes: &[Aabb]) {
+            self.events.clear();
+
+            // Forget the objects that are gone, and their pairs
+            let count = volumes.len();
+            let removed: Vec<[usize; 2]> = self.pairs.iter()
+                .filter(|pair| pair[1] >= count)
+                .copied()
+                .collect();
+
+            for pair in removed {
+                self.pairs.remove(&pair);
+                self.events.push(PairEvent::Removed(pair));
+            }
+
+            for endpoints in self.endpoints.iter_mut() {
+                endpoints.retain(|endpoint| endpoint.object < count);
+            }
+
+            // New objects start at the end of the lists, as if they were
+            // coming from infinitely far away
+            for index in self.volumes.len().min(count)..count {
+                for endpoints in self.endpoints.iter_mut() {
+                    endpoints.push(Endpoint { value: REAL_MAX, object: index, is_min: true });
+                    endpoints.push(Endpoint { value: REAL_MAX, object: index, is_min: false });
+                }
+            }
+
+            self.volumes.clear();
+            self.volumes.extend_from_slice(volumes);
+
+            for slot in 0..self.axes.len() {
+                let axis = self.axes[slot];
+
+                for endpoint in self.endpoints[slot].iter_mut() {
+                    let volume = &volumes[endpoint.object];
+                    let corner = if endpoint.is_min { volume.get_min() } else { volume.get_max() };
+                    endpoint.value = component(corner, axis);
+                }
+
+                self.sort_axis(slot);
+            }
         }
 
-        /// Calculates the vectorial product between this vector
-        /// and the one given as argument, and then replaces the
-        /// elements of this vector to the result of the product.
-        pub fn vec_prod_assign(&mut self, other: Vec3) {
-            *self = self.vec_prod(other);
+        fn get_potential_contacts(&self, limit: usize) -> Vec<PotentialContact> {
+            // When not all the axes are sorted the pairs may not overlap
+            // on the others
+            self.pairs.iter()
+                .filter(|[one, two]| self.volumes[*one].overlaps(&self.volumes[*two]))
+                .take(limit)
+                .map(|pair| PotentialContact { bodies: *pair })
+                .collect()
+        }
+
+        /// Returns the objects overlapping the given box, only checking
+        /// the ones starting before its end along the first sorted axis.
+        fn query(&self, volume: &Aabb) -> Vec<usize> {
+            let end = component(volume.get_max(), self.axes[0]);
+
+            self.endpoints[0].iter()
+                .take_while(|endpoint| endpoint.value <= end)
+                .filter(|endpoint| endpoint.is_min && self.volumes[endpoint.object].overlaps(volume))
+                .map(|endpoint| endpoint.object)
+                .collect()
         }
     }
 }
 
-pub mod particle {
+pub mod query {
     use super::precision::*;
     use super::core::*;
+    use super::particle::*;
+    use super::collide_fine::*;
+    use super::collide_coarse::*;
 
-    /// A particle is the simplest object that can be simulated in
-    /// the physics system.
-    #[allow(unused)]
-    #[derive(Debug)]
-    pub struct Particle {
-        /// Holds the linear position of the particle in world space
-        position: Vec3,
-        /// Holds the linear velocity of the particle in world space
-        velocity: Vec3,
-        /// Holds the acceleration of the particle.
-        acceleration: Vec3,
-        /// Holds the amount of damping applied to linear motion.
-        damping: Real,
-        /// Holds the inverse of the mass of the particle.
-        inverse_mass: Real,
-    }
-
-    pub trait ParticleLike{
-        fn integrate<T: AsReal>(&mut self, duration: T);
-        fn get_mass(&self) -> Real;
-        fn set_mass<T: AsReal>(&mut self, mass: T);
-        fn get_position(&self) -> Vec3;
-        fn set_position(&mut self, position: Vec3);
-        fn get_velocity(&self) -> Vec3;
-        fn set_velocity(&mut self, velocity: Vec3);
-        fn get_acceleration(&self) -> Vec3;
-        fn set_acceleration(&mut self, acceleration: Vec3);
-        fn get_damping(&self) -> Real;
-        fn set_damping<T: AsReal>(&mut self, damping: T);
+    /// A half-line starting at an origin and going along a direction,
+    /// used to query what lies along a line of sight.
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub struct Ray {
+        /// Holds the point the ray starts from.
+        pub origin: Vec3,
+        /// Holds the direction of the ray, which is always normalised.
+        pub direction: Vec3,
     }
 
-    impl ParticleLike for Particle {
-        /// Integrates the particle forward in time by the given amount.
-        fn integrate<T: AsReal>(&mut self, duration: T) {
-            let duration = duration.as_real();
+    impl Ray {
+        /// Creates a new ray from the given origin along the given
+        /// direction, which is normalised.
+        pub fn new(origin: Vec3, direction: Vec3) -> Self {
+            let mut direction = direction;
+            direction.normalize();
+            Ray { origin, direction }
+        }
 
-            if duration > 0 as Real {
-                // Update linear position
-                self.position += self.velocity * duration;
+        /// Returns the point of the ray at the given distance from its
+        /// origin.
+        pub fn get_point<T: AsReal>(&self, distance: T) -> Vec3 {
+            self.origin + self.direction * distance.as_real()
+        }
 
-                // Work out the acceleration from the force
-                let mut acc = self.acceleration.clone();
-                acc += self.acceleration * duration;
+        /// Returns the bounding box of the part of the ray up to the
+        /// given distance.
+        pub fn get_bounds<T: AsReal>(&self, max_distance: T) -> Aabb {
+            let end = self.get_point(max_distance);
+            Aabb::new(
+                Vec3::new(self.origin.x.min(end.x), self.origin.y.min(end.y), self.origin.z.min(end.z)),
+                Vec3::new(self.origin.x.max(end.x), self.origin.y.max(end.y), self.origin.z.max(end.z))
+            )
+        }
+    }
 
-                // Update linear velocity from the acceleration
-                self.velocity += acc * duration;
+    /// Holds where a ray hit an object. A ray starting inside an
+    /// object hits it at its origin, with a normal facing the ray.
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub struct RaycastHit {
+        /// Holds the distance along the ray to the hit.
+        pub distance: Real,
+        /// Holds the point hit, in world coordinates.
+        pub point: Vec3,
+        /// Holds the normal of the surface hit, in world coordinates.
+        pub normal: Vec3,
+        /// Holds the object hit: the body of a primitive or the index of
+        /// a particle. This is `None` for the scenery.
+        pub body: Option<usize>,
+    }
 
-                // Impose drag.
-                self.velocity *= self.damping.powf(duration);
-            }
+    impl RaycastHit {
+        /// Creates the hit at the given distance along the ray.
+        fn new(ray: &Ray, distance: Real, normal: Vec3, body: Option<usize>) -> Self {
+            RaycastHit { distance, point: ray.get_point(distance), normal, body }
         }
 
-        fn get_position(&self) -> Vec3 {
-            self.position.clone()
+        /// Creates the hit of a ray starting inside an object.
+        fn inside(ray: &Ray, body: Option<usize>) -> Self {
+            RaycastHit::new(ray, 0 as Real, -ray.direction, body)
         }
+    }
 
-        fn get_velocity(&self) -> Vec3 {
-            self.velocity.clone()
+    /// Casts the ray against a sphere, given by its centre and radius.
+    fn ray_and_sphere(ray: &Ray, centre: Vec3, radius: Real, max_distance: Real, body: Option<usize>) -> Option<RaycastHit> {
+        let offset = ray.origin - centre;
+        let b = offset.dot(ray.direction);
+        let c = offset.square_magnitude() - radius * radius;
+
+        // The origin is inside the sphere
+        if c <= 0 as Real {
+            return Some(RaycastHit::inside(ray, body));
         }
 
-        fn get_acceleration(&self) -> Vec3 {
-            self.acceleration.clone()
+        // The ray points away from the sphere, or misses it
+        let discriminant = b * b - c;
+
+        if b > 0 as Real || discriminant < 0 as Real {
+            return None;
         }
 
-        fn get_mass(&self) -> Real {
-            1.0 / self.inverse_mass
+        let distance = -b - discriminant.sqrt();
+
+        if distance > max_distance {
+            return None;
         }
 
-        fn set_mass<T: AsReal>(&mut self, mass: T) {
-            self.inverse_mass = 1.0 / mass.as_real();
+        let point = ray.get_point(distance);
+        Some(RaycastHit { distance, point, normal: (point - centre) * (1.0 / radius), body })
+    }
+
+    /// Casts a ray, given in the coordinates of an axis-aligned box,
+    /// against the box, returning the distance and normal of the hit.
+    /// A ray starting inside the box hits it at distance zero with no
+    /// normal.
+    fn ray_and_box_slabs(origin: Vec3, direction: Vec3, min: Vec3, max: Vec3, max_distance: Real) -> Option<(Real, Vec3)> {
+        let mut enter = 0 as Real;
+        let mut exit = max_distance;
+        let mut normal = Vec3::default();
+        let axes = [Vec3::new(1, 0, 0), Vec3::new(0, 1, 0), Vec3::new(0, 0, 1)];
+
+        for (i, axis) in axes.iter().enumerate() {
+            let (o, d, low, high) = match i {
+                0 => (origin.x, direction.x, min.x, max.x),
+                1 => (origin.y, direction.y, min.y, max.y),
+                _ => (origin.z, direction.z, min.z, max.z),
+            };
+
+            // Parallel to the slab: the ray must start between its planes
+            if d.abs() < REAL_EPSILON {
+                if o < low || o > high {
+                    return None;
+                }
+
+                continue;
+            }
+
+            let (mut near, mut far) = ((low - o) / d, (high - o) / d);
+
+            if near > far {
+                std::mem::swap(&mut near, &mut far);
+            }
+
+            // The ray enters the box through the face of the latest slab
+            if near > enter {
+                enter = near;
+                normal = *axis * if d > 0 as Real { -1 } else { 1 };
+            }
+
+            exit = exit.min(far);
+
+            if enter > exit {
+                return None;
+            }
         }
 
-        fn set_position(&mut self, position: Vec3) {
-            self.position = position;
+        Some((enter, normal))
+    }
+
+    /// Casts the ray against the axis-aligned box, up to the given
+    /// distance.
+    pub fn raycast_aabb<T: AsReal>(ray: &Ray, volume: &Aabb, max_distance: T) -> Option<RaycastHit> {
+        let (distance, normal) = ray_and_box_slabs(
+            ray.origin, ray.direction, volume.get_min(), volume.get_max(), max_distance.as_real()
+        )?;
+
+        if normal == Vec3::default() {
+            Some(RaycastHit::inside(ray, None))
+        } else {
+            Some(RaycastHit::new(ray, distance, normal, None))
         }
+    }
 
-        fn set_velocity(&mut self, velocity: Vec3) {
-            self.velocity = velocity;
+    /// Casts the ray against the sphere, up to the given distance.
+    pub fn raycast_sphere<T: AsReal>(ray: &Ray, sphere: &CollisionSphere, max_distance: T) -> Option<RaycastHit> {
+        ray_and_sphere(ray, sphere.primitive.get_position(), sphere.radius, max_distance.as_real(), sphere.primitive.body)
+    }
+
+    /// Casts the ray against the oriented box, up to the given distance.
+    pub fn raycast_box<T: AsReal>(ray: &Ray, cube: &CollisionBox, max_distance: T) -> Option<RaycastHit> {
+        // Work in the coordinates of the box, where it is axis-aligned
+        let transform = cube.primitive.get_transform();
+        let origin = transform.transform_inverse(ray.origin);
+        let direction = transform.transform_inverse_direction(ray.direction);
+
+        let (distance, normal) = ray_and_box_slabs(
+            origin, direction, -cube.half_size, cube.half_size, max_distance.as_real()
+        )?;
+
+        if normal == Vec3::default() {
+            Some(RaycastHit::inside(ray, cube.primitive.body))
+        } else {
+            Some(RaycastHit::new(ray, distance, transform.transform_direction(normal), cube.primitive.body))
         }
+    }
 
-        fn set_acceleration(&mut self, acceleration: Vec3) {
-            self.acceleration = acceleration;
+    /// Casts the ray against the half-space, up to the given distance.
+    pub fn raycast_plane<T: AsReal>(ray: &Ray, plane: &CollisionPlane, max_distance: T) -> Option<RaycastHit> {
+        let height = plane.direction.dot(ray.origin) - plane.offset;
+
+        // The origin is inside the half-space
+        if height <= 0 as Real {
+            return Some(RaycastHit::inside(ray, None));
         }
 
-        fn set_damping<T: AsReal>(&mut self, damping: T) {
-            self.damping = damping.as_real();
+        // The ray must go towards the plane
+        let speed = plane.direction.dot(ray.direction);
+
+        if speed >= 0 as Real {
+            return None;
         }
 
-        fn get_damping(&self) -> Real {
-            self.damping
+        let distance = -height / speed;
+
+        if distance > max_distance.as_real() {
+            return None;
         }
+
+        Some(RaycastHit::new(ray, distance, plane.direction, None))
     }
 
-    impl Particle {
-        /// Creates a new particle at the give position
-        pub fn from_position<T, U>(position: Vec3, mass: T, velocity: Vec3, acceleration: Vec3, damping: U) -> Self
-        where T: AsReal, U: AsReal {
-            let damping = damping.as_real();
-            let inverse_mass: Real = 1.0 / mass.as_real();
-            Particle { position, velocity, acceleration, damping, inverse_mass }
+    /// Casts the ray against the capsule, up to the given distance.
+    pub fn raycast_capsule<T: AsReal>(ray: &Ray, capsule: &CollisionCapsule, max_distance: T) -> Option<RaycastHit> {
+        let max_distance = max_distance.as_real();
+        let body = capsule.primitive.body;
+        let radius = capsule.radius;
+        let (start, end) = capsule.get_segment();
+
+        // The ends of the capsule are spheres
+        let mut closest = [
+            ray_and_sphere(ray, start, radius, max_distance, body),
+            ray_and_sphere(ray, end, radius, max_distance, body),
+        ].into_iter().flatten().min_by(|one, two| one.distance.total_cmp(&two.distance));
+
+        // Its side is a cylinder: remove the components along its axis
+        // and solve for the distance to the axis being the radius
+        let mut axis = end - start;
+        let length = axis.magnitude();
+
+        if length > 0 as Real {
+            axis *= 1.0 / length;
+
+            let offset = ray.origin - start;
+            let offset_across = offset - axis * offset.dot(axis);
+            let direction_across = ray.direction - axis * ray.direction.dot(axis);
+
+            let a = direction_across.square_magnitude();
+            let b = offset_across.dot(direction_across);
+            let c = offset_across.square_magnitude() - radius * radius;
+            let discriminant = b * b - a * c;
+
+            // The origin is inside the cylinder
+            if c <= 0 as Real && (0 as Real..=length).contains(&offset.dot(axis)) {
+                return Some(RaycastHit::inside(ray, body));
+            }
+
+            if a > REAL_EPSILON && discriminant >= 0 as Real {
+                let distance = (-b - discriminant.sqrt()) / a;
+                let along = (offset + ray.direction * distance).dot(axis);
+
+                if distance >= 0 as Real && distance <= max_distance && (0 as Real..=length).contains(&along) {
+                    let hit = RaycastHit::new(ray, distance, (offset_across + direction_across * distance) * (1.0 / radius), body);
+
+                    if !matches!(closest, Some(closest) if closest.distance <= hit.distance) {
+                        closest = Some(hit);
+                    }
+                }
+            }
         }
 
-        /// Creates a new particles and set the position automatically to the origin.
-        pub fn new<T, U>(mass: T, velocity: Vec3, acceleration: Vec3, damping: U) -> Self
-        where T: AsReal, U: AsReal {
-            let position = Vec3::default();
-            Particle::from_position(position, mass, velocity, acceleration, damping)
+        closest
+    }
+
+    /// Casts the ray against the particle, treated as a sphere of the
+    /// given radius, up to the given distance. The hit holds the given
+    /// index of the particle.
+    pub fn raycast_particle<T: AsReal, U: AsReal>(ray: &Ray, particle: &Particle, index: usize, radius: T, max_distance: U) -> Option<RaycastHit> {
+        ray_and_sphere(ray, particle.get_position(), radius.as_real(), max_distance.as_real(), Some(index))
+    }
+
+    /// Sweeps a sphere of the given radius along the ray against the
+    /// particle, treated as a sphere of the given particle radius, up to
+    /// the given distance. The distance of the hit is the one travelled
+    /// by the centre of the swept sphere, and its point is where the two
+    /// spheres touch.
+    pub fn sphere_cast_particle<T, U, V>(ray: &Ray, radius: T, particle: &Particle, index: usize, particle_radius: U, max_distance: V) -> Option<RaycastHit>
+    where T: AsReal, U: AsReal, V: AsReal {
+        let particle_radius = particle_radius.as_real();
+        let centre = particle.get_position();
+
+        // Sweeping a sphere against a sphere is casting a ray against
+        // a sphere with both radii
+        let mut hit = ray_and_sphere(
+            ray, centre, radius.as_real() + particle_radius, max_distance.as_real(), Some(index)
+        )?;
+
+        if hit.distance > 0 as Real {
+            hit.point = centre + hit.normal * particle_radius;
         }
+
+        Some(hit)
+    }
+
+    /// Returns true if the particle, treated as a sphere of the given
+    /// radius, overlaps the given box.
+    pub fn particle_overlaps_aabb<T: AsReal>(particle: &Particle, radius: T, volume: &Aabb) -> bool {
+        let radius = radius.as_real();
+        let position = particle.get_position();
+        let (min, max) = (volume.get_min(), volume.get_max());
+
+        // Find the closest point of the box to the particle
+        let closest = Vec3::new(
+            position.x.clamp(min.x, max.x),
+            position.y.clamp(min.y, max.y),
+            position.z.clamp(min.z, max.z)
+        );
+
+        (closest - position).square_magnitude() <= radius * radius
     }
 }